@@ -0,0 +1,160 @@
+use engine::{Entity, Rng, World};
+use engine::path::{Costs, DijkstraMap};
+use util::units::{Direction, Point};
+
+/// How a computer-controlled actor decides what to do on its turn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ai {
+    /// Wanders aimlessly, paying no attention to the player
+    Wanderer,
+    /// Chases the player on sight, but runs away once its health falls to
+    /// `flee_percent` percent of its maximum
+    Hunter { flee_percent: i32 },
+}
+
+/// What an actor is trying to do this turn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Behaviour {
+    Wander,
+    Chase,
+    Flee,
+}
+
+/// A single action taken by an actor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Walk(Direction),
+    Wait,
+}
+
+/// Maps leading to and away from the player, built when first needed and
+/// shared by every actor taking its turn on the same tick.
+///
+/// Both ignore other actors, so that they only need building again once
+/// the player moves. Actors step around each other as they follow them.
+pub struct PlayerMaps {
+    /// Where the player was when the maps were built
+    player: Option<Point>,
+    towards: Option<DijkstraMap>,
+    away: Option<DijkstraMap>,
+}
+
+impl PlayerMaps {
+    pub fn new() -> PlayerMaps {
+        PlayerMaps { player: None, towards: None, away: None }
+    }
+
+    /// Leads to the player
+    pub fn towards(&mut self, world: &World) -> &DijkstraMap {
+        self.update(world);
+
+        if self.towards.is_none() {
+            let player = world.player_pos();
+            self.towards = Some(DijkstraMap::new(&world.map, &[player], Costs::default(), |_| false));
+        }
+
+        self.towards.as_ref().unwrap()
+    }
+
+    /// Leads away from the player
+    pub fn away(&mut self, world: &World) -> &DijkstraMap {
+        self.update(world);
+
+        if self.away.is_none() {
+            let away = self.towards(world).fleeing(&world.map, Costs::default(), |_| false);
+            self.away = Some(away);
+        }
+
+        self.away.as_ref().unwrap()
+    }
+
+    /// Forgets the maps if the player has moved since they were built
+    fn update(&mut self, world: &World) {
+        let player = Some(world.player_pos());
+
+        if player != self.player {
+            self.player = player;
+            self.towards = None;
+            self.away = None;
+        }
+    }
+}
+
+impl Ai {
+    /// Decides how an entity should behave given the state of the world
+    pub fn behaviour(&self, entity: Entity, world: &World) -> Behaviour {
+        match *self {
+            Ai::Wanderer => Behaviour::Wander,
+            Ai::Hunter { flee_percent } => {
+                let entities = &world.entities;
+
+                if entities.health(entity) * 100 <= entities.max_health(entity) * flee_percent {
+                    Behaviour::Flee
+                } else if entities.position(entity).map_or(false, |pos| world.map.is_visible(pos)) {
+                    // Sight is symmetric, so if the player can see the
+                    // actor then the actor can see the player
+                    Behaviour::Chase
+                } else {
+                    Behaviour::Wander
+                }
+            }
+        }
+    }
+
+    /// Chooses an entity's action for this turn
+    pub fn choose_action(&self, entity: Entity, world: &World, maps: &mut PlayerMaps, rng: &mut Rng) -> Action {
+        let pos = match world.entities.position(entity) {
+            Some(pos) => pos,
+            None => return Action::Wait,
+        };
+        let target = world.player_pos();
+
+        let moves: Vec<Direction> = Direction::all().iter()
+                                                     .cloned()
+                                                     .filter(|&d| world.is_free(pos.move_dir(d)))
+                                                     .collect();
+
+        match self.behaviour(entity, world) {
+            Behaviour::Wander => {
+                match rng.choose(&moves) {
+                    Some(&d) => Action::Walk(d),
+                    None => Action::Wait,
+                }
+            }
+            Behaviour::Chase => {
+                // Walking into the player attacks them
+                if let Some(&d) = Direction::all().iter().find(|&&d| pos.move_dir(d) == target) {
+                    return Action::Walk(d);
+                }
+
+                // Find a way around whatever is in between, going straight
+                // for the player if there is none
+                if let Some(d) = maps.towards(world).downhill(pos, |p| !world.is_free(p)) {
+                    return Action::Walk(d);
+                }
+
+                let best = moves.iter().min_by_key(|&&d| distance_squared(pos.move_dir(d), target));
+                match best {
+                    Some(&d) if distance_squared(pos.move_dir(d), target) < distance_squared(pos, target) => Action::Walk(d),
+                    _ => Action::Wait,
+                }
+            }
+            Behaviour::Flee => {
+                if let Some(d) = maps.away(world).downhill(pos, |p| !world.is_free(p)) {
+                    return Action::Walk(d);
+                }
+
+                let best = moves.iter().max_by_key(|&&d| distance_squared(pos.move_dir(d), target));
+                match best {
+                    Some(&d) if distance_squared(pos.move_dir(d), target) > distance_squared(pos, target) => Action::Walk(d),
+                    _ => Action::Wait,
+                }
+            }
+        }
+    }
+}
+
+fn distance_squared(a: Point, b: Point) -> i32 {
+    let (dx, dy) = (a.x - b.x, a.y - b.y);
+    dx * dx + dy * dy
+}
@@ -0,0 +1,15 @@
+use engine::ecs::{Ecs, Entity};
+
+use std::cmp;
+
+/// Calculates how much damage a blow from `attacker` does to `defender`
+pub fn damage(entities: &Ecs, attacker: Entity, defender: Entity) -> i32 {
+    cmp::max(entities.attack(attacker) - entities.defence(defender), 0)
+}
+
+/// Resolves a single melee attack, returning the damage dealt
+pub fn melee(entities: &mut Ecs, attacker: Entity, defender: Entity) -> i32 {
+    let damage = damage(entities, attacker, defender);
+    entities.change_health(defender, -damage);
+    damage
+}
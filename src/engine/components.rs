@@ -0,0 +1,167 @@
+//! The components entities are built from
+//!
+//! Each is plain data describing one aspect of an entity. Anything which
+//! needs more than one component, such as working out an entity's attack
+//! including what it is wearing, lives on `Ecs` instead.
+
+use engine::{Item, Modifiers, Slot};
+use engine::scheduler::ACTION_THRESHOLD;
+use engine::status::Status;
+use util::units::{Color, Point};
+
+/// Where an entity is on the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position(pub Point);
+
+/// What an entity is called
+#[derive(Clone, Debug, PartialEq)]
+pub struct Name(pub String);
+
+/// How much punishment an entity can take before it dies
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    pub current: i32,
+    /// The maximum before any equipment is taken into account
+    pub base_max: i32,
+}
+
+impl Health {
+    /// Full health out of `max`
+    pub fn new(max: i32) -> Health {
+        Health { current: max, base_max: max }
+    }
+
+    /// Returns true if health is equal to or below zero
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+}
+
+/// How quickly an entity acts, and how close it is to its next action.
+/// See `scheduler` for how these are used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Energy {
+    /// The energy gained every tick
+    pub speed: i32,
+    pub energy: i32,
+}
+
+impl Energy {
+    /// Energy for an entity of the given speed, which has just acted
+    pub fn new(speed: i32) -> Energy {
+        Energy { speed: speed, energy: 0 }
+    }
+
+    /// Energy for an entity of the given speed, which can act straight away
+    pub fn ready(speed: i32) -> Energy {
+        Energy { speed: speed, energy: ACTION_THRESHOLD }
+    }
+}
+
+/// How an entity is drawn on the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Renderable {
+    pub glyph: char,
+    pub color: Color,
+}
+
+/// How hard an entity hits and how well it shrugs off blows, before any
+/// equipment is taken into account
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub attack: i32,
+    pub defence: i32,
+}
+
+/// The items an entity is carrying
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    /// The most items the entity can carry
+    pub capacity: usize,
+}
+
+impl Inventory {
+    pub fn new(capacity: usize) -> Inventory {
+        Inventory { items: Vec::new(), capacity: capacity }
+    }
+
+    /// Returns true if nothing more can be carried
+    pub fn is_full(&self) -> bool {
+        self.items.len() >= self.capacity
+    }
+
+    /// Adds an item, handing it back if there is no room
+    pub fn add(&mut self, item: Item) -> Result<(), Item> {
+        if self.is_full() {
+            Err(item)
+        } else {
+            self.items.push(item);
+            Ok(())
+        }
+    }
+
+    /// Removes an item, returning it if it was there
+    pub fn remove(&mut self, index: usize) -> Option<Item> {
+        if index < self.items.len() {
+            Some(self.items.remove(index))
+        } else {
+            None
+        }
+    }
+}
+
+/// What an entity is wearing
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Equipment {
+    /// What is worn in each slot, indexed by `Slot::index`
+    slots: [Option<Item>; 3],
+}
+
+impl Equipment {
+    pub fn new() -> Equipment {
+        Equipment::default()
+    }
+
+    /// Returns the item worn in a slot, if there is one
+    pub fn get(&self, slot: Slot) -> Option<&Item> {
+        self.slots[slot.index()].as_ref()
+    }
+
+    /// Wears an item in its slot, returning whatever was worn there before.
+    ///
+    /// Items which cannot be worn are handed back as an error.
+    pub fn wear(&mut self, item: Item) -> Result<Option<Item>, Item> {
+        let slot = match item.slot() {
+            Some(slot) => slot,
+            None => return Err(item),
+        };
+
+        Ok(self.slots[slot.index()].replace(item))
+    }
+
+    /// Takes off whatever is worn in a slot
+    pub fn take_off(&mut self, slot: Slot) -> Option<Item> {
+        self.slots[slot.index()].take()
+    }
+
+    /// Returns the sum of the modifiers of everything being worn
+    pub fn modifiers(&self) -> Modifiers {
+        self.slots.iter().filter_map(|item| item.as_ref()).fold(Modifiers::default(), |total, item| {
+            let modifiers = item.modifiers();
+            Modifiers::new(total.attack + modifiers.attack,
+                           total.defence + modifiers.defence,
+                           total.max_health + modifiers.max_health)
+        })
+    }
+}
+
+/// A status inflicted on anything the entity hits for at least one damage,
+/// such as a spider's poison
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OnHit(pub Status);
+
+/// Marks the entity the player controls. It takes its turns from the
+/// player's commands rather than from an `Ai`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Controlled;
@@ -0,0 +1,146 @@
+//! Dice written the way tabletop games do, such as `3d6+2`
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The most dice which can be written in one roll
+pub const MAX_COUNT: u32 = 100;
+
+/// The most sides a written die can have
+pub const MAX_SIDES: u32 = 1000;
+
+/// Some number of dice with the same number of sides, plus a modifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dice {
+    pub count: u32,
+    pub sides: u32,
+    pub modifier: i32,
+}
+
+impl Dice {
+    pub fn new(count: u32, sides: u32, modifier: i32) -> Dice {
+        Dice { count: count, sides: sides, modifier: modifier }
+    }
+
+    /// The lowest total the dice can roll
+    pub fn min(&self) -> i32 {
+        let lowest = if self.sides == 0 { 0 } else { self.count as i64 };
+        saturate(lowest + self.modifier as i64)
+    }
+
+    /// The highest total the dice can roll
+    pub fn max(&self) -> i32 {
+        saturate((self.count as i64).saturating_mul(self.sides as i64).saturating_add(self.modifier as i64))
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+
+        if self.modifier != 0 {
+            write!(f, "{:+}", self.modifier)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses dice such as `3d6+2`, `d20` or `2d4-1`. A missing count means one
+/// die, and a missing modifier means zero. At most `MAX_COUNT` dice of at most
+/// `MAX_SIDES` sides can be written.
+impl FromStr for Dice {
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Dice, DiceError> {
+        let invalid = || DiceError(s.to_string());
+        let s = s.trim();
+
+        let d = s.find(|c| c == 'd' || c == 'D').ok_or_else(invalid)?;
+        let count = match &s[..d] {
+            "" => 1,
+            count => count.parse().map_err(|_| invalid())?,
+        };
+
+        let rest = &s[d + 1..];
+        let (sides, modifier) = match rest.find(|c| c == '+' || c == '-') {
+            Some(sign) => (&rest[..sign], rest[sign..].parse().map_err(|_| invalid())?),
+            None => (rest, 0),
+        };
+
+        let sides = sides.parse().map_err(|_| invalid())?;
+
+        if count == 0 || sides == 0 || count > MAX_COUNT || sides > MAX_SIDES {
+            return Err(invalid());
+        }
+
+        Ok(Dice::new(count, sides, modifier))
+    }
+}
+
+/// The error returned when dice cannot be parsed
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceError(pub String);
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid dice {:?}, expected something like \"3d6+2\"", self.0)
+    }
+}
+
+impl Error for DiceError {}
+
+/// Converts a total to an `i32`, stopping at the limits rather than wrapping
+pub fn saturate(total: i64) -> i32 {
+    if total > i32::MAX as i64 {
+        i32::MAX
+    } else if total < i32::MIN as i64 {
+        i32::MIN
+    } else {
+        total as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dice, MAX_COUNT, MAX_SIDES};
+
+    #[test]
+    fn parses_written_dice() {
+        assert_eq!("3d6+2".parse(), Ok(Dice::new(3, 6, 2)));
+        assert_eq!("d20".parse(), Ok(Dice::new(1, 20, 0)));
+        assert_eq!("2D4-1".parse(), Ok(Dice::new(2, 4, -1)));
+        assert_eq!(" 1d8 ".parse(), Ok(Dice::new(1, 8, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_dice() {
+        for text in ["", "6", "d", "0d6", "2d0", "2d6+", "2d6+x", "-1d6", "xd6", "1d4294967295"].iter() {
+            assert!(text.parse::<Dice>().is_err(), "{:?} should not parse", text);
+        }
+
+        assert!(format!("{}d6", MAX_COUNT + 1).parse::<Dice>().is_err());
+        assert!(format!("1d{}", MAX_SIDES + 1).parse::<Dice>().is_err());
+        assert!(format!("{}d{}", MAX_COUNT, MAX_SIDES).parse::<Dice>().is_ok());
+    }
+
+    #[test]
+    fn displays_as_written() {
+        for text in ["3d6+2", "1d20", "2d4-1"].iter() {
+            assert_eq!(text.parse::<Dice>().unwrap().to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn min_and_max() {
+        let dice = Dice::new(3, 6, 2);
+        assert_eq!(dice.min(), 5);
+        assert_eq!(dice.max(), 20);
+
+        let huge = Dice::new(u32::max_value(), u32::max_value(), i32::max_value());
+        assert_eq!(huge.max(), i32::max_value());
+        assert_eq!(huge.min(), i32::max_value());
+        assert_eq!(Dice::new(2, 0, 1).min(), 1);
+    }
+}
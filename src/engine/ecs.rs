@@ -0,0 +1,476 @@
+//! Entities and the components they are made of
+//!
+//! An `Entity` is only an id. Everything about it is kept in one `Storage`
+//! per kind of component in an `Ecs`, so the player, monsters and anything
+//! else on a level differ only in which components they have. The player is
+//! the entity with the `Controlled` component.
+//!
+//! Storages iterate in the order entities were created, which keeps turns
+//! taken in a fixed order and so keeps replays reproducible.
+
+use engine::{Ai, Item, Modifiers, Slot};
+use engine::components::{Controlled, Energy, Equipment, Health, Inventory, Name, OnHit, Position, Renderable, Stats};
+use engine::status::{Added, Status, StatusKind, Statuses};
+use util::units::{Color, Point};
+
+use std::cmp;
+use std::collections::btree_map::{self, BTreeMap};
+use std::collections::BTreeSet;
+
+/// A single thing in the world, identified by the components stored for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity(u32);
+
+/// Every component of one kind, by entity
+#[derive(Clone, Debug)]
+pub struct Storage<T> {
+    components: BTreeMap<Entity, T>,
+}
+
+impl<T> Storage<T> {
+    pub fn new() -> Storage<T> {
+        Storage { components: BTreeMap::new() }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.components.get(&entity)
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.components.get_mut(&entity)
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.components.contains_key(&entity)
+    }
+
+    /// Gives an entity the component, replacing any it already had
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        self.components.insert(entity, component);
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        self.components.remove(&entity)
+    }
+
+    /// Every entity with the component, oldest first
+    pub fn entities(&self) -> Vec<Entity> {
+        self.components.keys().cloned().collect()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, Entity, T> {
+        self.components.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, Entity, T> {
+        self.components.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Storage<T> {
+        Storage::new()
+    }
+}
+
+/// Every entity on a level and all of their components
+#[derive(Clone, Debug, Default)]
+pub struct Ecs {
+    next_id: u32,
+    alive: BTreeSet<Entity>,
+    pub positions: Storage<Position>,
+    pub names: Storage<Name>,
+    pub healths: Storage<Health>,
+    pub renderables: Storage<Renderable>,
+    pub stats: Storage<Stats>,
+    pub ais: Storage<Ai>,
+    pub energies: Storage<Energy>,
+    pub inventories: Storage<Inventory>,
+    pub equipment: Storage<Equipment>,
+    pub statuses: Storage<Statuses>,
+    pub on_hits: Storage<OnHit>,
+    pub controlled: Storage<Controlled>,
+}
+
+impl Ecs {
+    pub fn new() -> Ecs {
+        Ecs::default()
+    }
+
+    /// Creates an entity with no components
+    pub fn create(&mut self) -> Entity {
+        let entity = Entity(self.next_id);
+        self.next_id += 1;
+        self.alive.insert(entity);
+        entity
+    }
+
+    /// Creates an entity with every component set on a builder
+    pub fn spawn(&mut self, builder: EntityBuilder) -> Entity {
+        let entity = self.create();
+
+        if let Some(c) = builder.position { self.positions.insert(entity, c); }
+        if let Some(c) = builder.name { self.names.insert(entity, c); }
+        if let Some(c) = builder.health { self.healths.insert(entity, c); }
+        if let Some(c) = builder.renderable { self.renderables.insert(entity, c); }
+        if let Some(c) = builder.stats { self.stats.insert(entity, c); }
+        if let Some(c) = builder.ai { self.ais.insert(entity, c); }
+        if let Some(c) = builder.energy { self.energies.insert(entity, c); }
+        if let Some(c) = builder.inventory { self.inventories.insert(entity, c); }
+        if let Some(c) = builder.equipment { self.equipment.insert(entity, c); }
+        if let Some(c) = builder.statuses { self.statuses.insert(entity, c); }
+        if let Some(c) = builder.on_hit { self.on_hits.insert(entity, c); }
+        if let Some(c) = builder.controlled { self.controlled.insert(entity, c); }
+
+        entity
+    }
+
+    /// Removes an entity, handing back its components so that it can be
+    /// spawned again somewhere else
+    pub fn take(&mut self, entity: Entity) -> EntityBuilder {
+        self.alive.remove(&entity);
+
+        EntityBuilder {
+            position: self.positions.remove(entity),
+            name: self.names.remove(entity),
+            health: self.healths.remove(entity),
+            renderable: self.renderables.remove(entity),
+            stats: self.stats.remove(entity),
+            ai: self.ais.remove(entity),
+            energy: self.energies.remove(entity),
+            inventory: self.inventories.remove(entity),
+            equipment: self.equipment.remove(entity),
+            statuses: self.statuses.remove(entity),
+            on_hit: self.on_hits.remove(entity),
+            controlled: self.controlled.remove(entity),
+        }
+    }
+
+    /// Removes an entity and all of its components
+    pub fn destroy(&mut self, entity: Entity) {
+        self.take(entity);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.contains(&entity)
+    }
+
+    /// Every entity, oldest first
+    pub fn entities(&self) -> Vec<Entity> {
+        self.alive.iter().cloned().collect()
+    }
+
+    /// Returns the entity standing at a point, if there is one
+    pub fn entity_at(&self, loc: Point) -> Option<Entity> {
+        self.positions.iter().find(|&(_, p)| p.0 == loc).map(|(&e, _)| e)
+    }
+
+    /// Returns the entity the player controls, if it is here
+    pub fn controlled_entity(&self) -> Option<Entity> {
+        self.controlled.iter().next().map(|(&e, _)| e)
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Point> {
+        self.positions.get(entity).map(|p| p.0)
+    }
+
+    /// What an entity is called, or "something" if it has no name
+    pub fn name(&self, entity: Entity) -> &str {
+        self.names.get(entity).map_or("something", |n| &n.0)
+    }
+
+    /// Returns the entity's current health, or zero if it has none
+    pub fn health(&self, entity: Entity) -> i32 {
+        self.healths.get(entity).map_or(0, |h| h.current)
+    }
+
+    /// Returns true if the entity has health and it has run out
+    pub fn is_dead(&self, entity: Entity) -> bool {
+        self.healths.get(entity).map_or(false, |h| h.is_dead())
+    }
+
+    /// Returns the sum of the modifiers of everything the entity is wearing
+    pub fn modifiers(&self, entity: Entity) -> Modifiers {
+        self.equipment.get(entity).map_or(Modifiers::default(), |e| e.modifiers())
+    }
+
+    /// The entity's maximum health, including equipment
+    pub fn max_health(&self, entity: Entity) -> i32 {
+        self.healths.get(entity).map_or(0, |h| h.base_max) + self.modifiers(entity).max_health
+    }
+
+    /// How hard the entity hits, including equipment
+    pub fn attack(&self, entity: Entity) -> i32 {
+        self.stats.get(entity).map_or(0, |s| s.attack) + self.modifiers(entity).attack
+    }
+
+    /// How well the entity shrugs off blows, including equipment
+    pub fn defence(&self, entity: Entity) -> i32 {
+        self.stats.get(entity).map_or(0, |s| s.defence) + self.modifiers(entity).defence
+    }
+
+    /// The items an entity is carrying
+    pub fn items(&self, entity: Entity) -> &[Item] {
+        self.inventories.get(entity).map_or(&[], |i| &i.items)
+    }
+
+    /// Returns true if the entity is under a status effect
+    pub fn has_status(&self, entity: Entity, kind: StatusKind) -> bool {
+        self.statuses.get(entity).map_or(false, |s| s.has(kind))
+    }
+
+    /// Gives an entity a status, stacking it with any it already has
+    pub fn add_status(&mut self, entity: Entity, status: Status) -> Added {
+        if !self.statuses.contains(entity) {
+            self.statuses.insert(entity, Statuses::new());
+        }
+
+        self.statuses.get_mut(entity).map_or(Added::Unchanged, |s| s.add(status))
+    }
+
+    /// The energy an entity gains each tick, including haste
+    pub fn speed(&self, entity: Entity) -> i32 {
+        let speed = self.energies.get(entity).map_or(0, |e| e.speed);
+        if self.has_status(entity, StatusKind::Haste) { speed * 2 } else { speed }
+    }
+
+    /// Returns the item an entity is wearing in a slot, if there is one
+    pub fn equipped(&self, entity: Entity, slot: Slot) -> Option<&Item> {
+        self.equipment.get(entity).and_then(|e| e.get(slot))
+    }
+
+    /// Puts on an item, returning whatever was worn in its slot before.
+    ///
+    /// Items which cannot be worn, or entities which cannot wear anything,
+    /// hand the item back as an error.
+    pub fn equip(&mut self, entity: Entity, item: Item) -> Result<Option<Item>, Item> {
+        let previous = match self.equipment.get_mut(entity) {
+            Some(equipment) => equipment.wear(item)?,
+            None => return Err(item),
+        };
+
+        self.clamp_health(entity);
+        Ok(previous)
+    }
+
+    /// Takes off whatever an entity is wearing in a slot
+    pub fn unequip(&mut self, entity: Entity, slot: Slot) -> Option<Item> {
+        let item = self.equipment.get_mut(entity).and_then(|e| e.take_off(slot));
+        self.clamp_health(entity);
+        item
+    }
+
+    /// Changes an entity's health by `amount`, which is negative for damage.
+    /// Healing never goes past the maximum.
+    pub fn change_health(&mut self, entity: Entity, amount: i32) {
+        let max_health = self.max_health(entity);
+
+        if let Some(health) = self.healths.get_mut(entity) {
+            health.current = if amount > 0 { cmp::min(health.current + amount, max_health) }
+                             else { health.current + amount };
+        }
+    }
+
+    /// Keeps health from exceeding a maximum lowered by a change of equipment
+    fn clamp_health(&mut self, entity: Entity) {
+        let max_health = self.max_health(entity);
+
+        if let Some(health) = self.healths.get_mut(entity) {
+            health.current = cmp::min(health.current, max_health);
+        }
+    }
+}
+
+/// The components of an entity which has not been spawned yet
+///
+/// # Example
+///
+/// ```
+/// use verbonia::engine::ecs::{Ecs, EntityBuilder};
+/// use verbonia::util::units::Point;
+///
+/// let mut ecs = Ecs::new();
+/// let dog = ecs.spawn(EntityBuilder::new().with_name("Dog").with_position(Point::zero()));
+/// assert_eq!(ecs.name(dog), "Dog");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EntityBuilder {
+    position: Option<Position>,
+    name: Option<Name>,
+    health: Option<Health>,
+    renderable: Option<Renderable>,
+    stats: Option<Stats>,
+    ai: Option<Ai>,
+    energy: Option<Energy>,
+    inventory: Option<Inventory>,
+    equipment: Option<Equipment>,
+    statuses: Option<Statuses>,
+    on_hit: Option<OnHit>,
+    controlled: Option<Controlled>,
+}
+
+impl EntityBuilder {
+    pub fn new() -> EntityBuilder {
+        EntityBuilder::default()
+    }
+
+    pub fn with_position(mut self, pos: Point) -> EntityBuilder {
+        self.position = Some(Position(pos));
+        self
+    }
+
+    pub fn with_name(mut self, name: &str) -> EntityBuilder {
+        self.name = Some(Name(name.to_string()));
+        self
+    }
+
+    pub fn with_health(mut self, health: Health) -> EntityBuilder {
+        self.health = Some(health);
+        self
+    }
+
+    /// Sets the character and colour used to draw the entity
+    pub fn with_glyph(mut self, glyph: char, color: Color) -> EntityBuilder {
+        self.renderable = Some(Renderable { glyph: glyph, color: color });
+        self
+    }
+
+    /// Sets how hard the entity hits and how well it shrugs off blows
+    pub fn with_stats(mut self, attack: i32, defence: i32) -> EntityBuilder {
+        self.stats = Some(Stats { attack: attack, defence: defence });
+        self
+    }
+
+    /// Makes the entity computer-controlled
+    pub fn with_ai(mut self, ai: Ai) -> EntityBuilder {
+        self.ai = Some(ai);
+        self
+    }
+
+    /// Lets the entity take turns, gaining energy at the given speed
+    pub fn with_energy(mut self, energy: Energy) -> EntityBuilder {
+        self.energy = Some(energy);
+        self
+    }
+
+    pub fn with_inventory(mut self, inventory: Inventory) -> EntityBuilder {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    pub fn with_equipment(mut self, equipment: Equipment) -> EntityBuilder {
+        self.equipment = Some(equipment);
+        self
+    }
+
+    pub fn with_statuses(mut self, statuses: Statuses) -> EntityBuilder {
+        self.statuses = Some(statuses);
+        self
+    }
+
+    /// Makes the entity inflict a status on anything it hits
+    pub fn with_on_hit(mut self, status: Status) -> EntityBuilder {
+        self.on_hit = Some(OnHit(status));
+        self
+    }
+
+    /// Makes the entity the one the player controls
+    pub fn controlled(mut self) -> EntityBuilder {
+        self.controlled = Some(Controlled);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ecs, EntityBuilder};
+    use engine::{Ai, Game};
+    use engine::components::{Energy, Health};
+    use util::units::{Color, Point};
+
+    fn rat(pos: Point) -> EntityBuilder {
+        EntityBuilder::new().with_name("Rat").with_position(pos).with_health(Health::new(5))
+    }
+
+    #[test]
+    fn spawns_and_destroys_entities() {
+        let mut ecs = Ecs::new();
+        let first = ecs.spawn(rat(Point::new(1, 1)));
+        let second = ecs.spawn(rat(Point::new(2, 1)));
+
+        assert_eq!(ecs.entities(), vec![first, second]);
+        assert_eq!(ecs.entity_at(Point::new(2, 1)), Some(second));
+
+        ecs.destroy(first);
+
+        assert!(!ecs.is_alive(first));
+        assert!(ecs.is_alive(second));
+        assert_eq!(ecs.entities(), vec![second]);
+        assert_eq!(ecs.entity_at(Point::new(1, 1)), None);
+        assert!(!ecs.names.contains(first) && !ecs.healths.contains(first));
+
+        // Ids are never handed out twice
+        let third = ecs.spawn(rat(Point::new(1, 1)));
+        assert!(third != first && third != second);
+    }
+
+    #[test]
+    fn taken_components_can_be_spawned_again() {
+        let mut here = Ecs::new();
+        let mut there = Ecs::new();
+        let rat = here.spawn(rat(Point::new(3, 4)));
+        here.change_health(rat, -2);
+
+        let builder = here.take(rat);
+
+        assert!(!here.is_alive(rat));
+        assert!(here.positions.is_empty() && here.names.is_empty() && here.healths.is_empty());
+
+        let moved = there.spawn(builder.with_position(Point::new(7, 7)));
+
+        assert_eq!(there.name(moved), "Rat");
+        assert_eq!(there.health(moved), 3);
+        assert_eq!(there.position(moved), Some(Point::new(7, 7)));
+    }
+
+    #[test]
+    fn builder_attaches_only_the_components_given() {
+        let mut ecs = Ecs::new();
+        let hunter = ecs.spawn(EntityBuilder::new()
+                                   .with_glyph('h', Color::new(1, 2, 3))
+                                   .with_stats(4, 2)
+                                   .with_ai(Ai::Wanderer)
+                                   .with_energy(Energy::new(150))
+                                   .controlled());
+
+        assert_eq!(ecs.renderables.get(hunter).map(|r| r.glyph), Some('h'));
+        assert_eq!((ecs.attack(hunter), ecs.defence(hunter)), (4, 2));
+        assert_eq!(ecs.ais.get(hunter), Some(&Ai::Wanderer));
+        assert_eq!(ecs.speed(hunter), 150);
+        assert_eq!(ecs.controlled_entity(), Some(hunter));
+
+        assert_eq!(ecs.position(hunter), None);
+        assert_eq!(ecs.name(hunter), "something");
+        assert!(!ecs.healths.contains(hunter) && !ecs.inventories.contains(hunter));
+    }
+
+    #[test]
+    fn same_seed_places_the_same_entities() {
+        let first = Game::with_seed(99);
+        let second = Game::with_seed(99);
+
+        let positions = |game: &Game| -> Vec<_> {
+            game.world.entities.entities().into_iter().map(|e| game.world.entities.position(e)).collect()
+        };
+
+        assert_eq!(positions(&first), positions(&second));
+    }
+}
@@ -0,0 +1,86 @@
+//! Field of view, computed with recursive shadowcasting
+//!
+//! The area around the viewer is split into eight octants. Each octant is
+//! scanned row by row moving away from the viewer, and every opaque tile
+//! found casts a shadow which hides the tiles behind it in later rows.
+
+use util::units::Point;
+
+/// Transforms from octant-local coordinates into map coordinates
+static OCTANTS: [(i32, i32, i32, i32); 8] = [
+    ( 1,  0,  0,  1),
+    ( 0,  1,  1,  0),
+    ( 0, -1,  1,  0),
+    (-1,  0,  0,  1),
+    (-1,  0,  0, -1),
+    ( 0, -1, -1,  0),
+    ( 0,  1, -1,  0),
+    ( 1,  0,  0, -1),
+];
+
+/// Calculates which points can be seen from `origin` within `radius`.
+///
+/// `is_opaque` reports whether a point blocks sight and must return true for
+/// any point outside the map. `mark_visible` is called for every point that
+/// can be seen, including the origin and the opaque points bounding the view.
+pub fn compute<F, G>(origin: Point, radius: i32, is_opaque: F, mut mark_visible: G)
+    where F: Fn(Point) -> bool, G: FnMut(Point) {
+    mark_visible(origin);
+
+    for &octant in OCTANTS.iter() {
+        cast_light(origin, radius, 1, 1.0, 0.0, octant, &is_opaque, &mut mark_visible);
+    }
+}
+
+/// Scans one octant from `row` outwards, between the `start` and `end` slopes
+fn cast_light<F, G>(origin: Point, radius: i32, row: i32, start: f32, end: f32,
+                    octant: (i32, i32, i32, i32), is_opaque: &F, mark_visible: &mut G)
+    where F: Fn(Point) -> bool, G: FnMut(Point) {
+    if start < end {
+        return;
+    }
+
+    let (xx, xy, yx, yy) = octant;
+    let radius_squared = radius * radius;
+    let mut start = start;
+    let mut next_start = start;
+
+    for distance in row..radius + 1 {
+        let dy = -distance;
+        let mut blocked = false;
+
+        for dx in -distance..1 {
+            let pos = origin + (dx * xx + dy * xy, dx * yx + dy * yy);
+
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+            if start < right_slope {
+                continue;
+            } else if end > left_slope {
+                break;
+            }
+
+            if dx * dx + dy * dy <= radius_squared {
+                mark_visible(pos);
+            }
+
+            if blocked {
+                if is_opaque(pos) {
+                    next_start = right_slope;
+                } else {
+                    blocked = false;
+                    start = next_start;
+                }
+            } else if is_opaque(pos) && distance < radius {
+                blocked = true;
+                cast_light(origin, radius, distance + 1, start, left_slope, octant, is_opaque, mark_visible);
+                next_start = right_slope;
+            }
+        }
+
+        if blocked {
+            break;
+        }
+    }
+}
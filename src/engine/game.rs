@@ -1,33 +1,195 @@
-use engine::World;
+use engine::{Rng, Slot, World};
+use engine::level::{self, ITEM_COUNT, MONSTER_COUNT};
+use engine::log;
+use engine::replay::{Recorder, ReplayError};
+use engine::save::{self, SaveError};
+use engine::scheduler::{self, ActionKind, TICKS_PER_TURN};
+use engine::systems;
 
 use util::units::Direction;
 
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 /// The entire game state.
 pub struct Game {
     pub world: World,
+    /// The number of turns the player has taken
+    pub turn: u32,
+    /// The number of scheduler ticks since the game began. See `scheduler`.
+    pub time: u64,
+    /// The seed the game was created from, if it is known. Games loaded
+    /// from saves older than version 5 do not record it.
+    pub seed: Option<u64>,
+    pub rng: Rng,
+    /// Where the player's commands are being recorded, if anywhere
+    recorder: Option<Recorder>,
+    /// Set when the player has used up their turn and the systems have not
+    /// yet run
+    turn_taken: bool,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Command {
     Walk(Direction),
+    /// Picks up the topmost item beneath the player
+    PickUp,
+    /// Drops the item at an index in the player's inventory
+    Drop(usize),
+    /// Uses the item at an index in the player's inventory, putting it on
+    /// if it is equipment
+    Use(usize),
+    /// Takes off whatever the player is wearing in a slot
+    Unequip(Slot),
+    /// Takes the stairs down
+    Descend,
+    /// Takes the stairs up
+    Ascend,
 }
 
 impl Game {
-    /// Creates a new game.
+    /// Creates a new game with a seed taken from the system clock.
     pub fn new() -> Game {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+                                    .map(|d| d.as_secs() ^ d.subsec_nanos() as u64)
+                                    .unwrap_or(0);
+
+        Game::with_seed(seed)
+    }
+
+    /// Creates a new game whose dungeon is generated from `seed`.
+    pub fn with_seed(seed: u64) -> Game {
+        let mut rng = Rng::new(seed);
+        let map = level::generate_map(1, &mut rng);
+
+        let mut world = World::new(map);
+        world.spawn_monsters(MONSTER_COUNT, &mut rng);
+        world.spawn_items(ITEM_COUNT, &mut rng);
+
+        log::set_turn(0);
+        log::debug(&format!("Generated a new game from seed {}.", seed));
+
+        Game {
+            world: world,
+            turn: 0,
+            time: 0,
+            seed: Some(seed),
+            rng: rng,
+            recorder: None,
+            turn_taken: false,
+        }
+    }
+
+    /// Builds a game from its parts, as when loading a save.
+    pub fn from_parts(world: World, turn: u32, time: u64, seed: Option<u64>, rng: Rng) -> Game {
         Game {
-            world: World::new(),
+            world: world,
+            turn: turn,
+            time: time,
+            seed: seed,
+            rng: rng,
+            recorder: None,
+            turn_taken: false,
         }
     }
 
+    /// Loads a previously saved game.
+    pub fn load<P>(path: P) -> Result<Game, SaveError> where P: AsRef<Path> {
+        let (game, messages) = save::load_from_file(path)?;
+        log::replace(messages);
+        Ok(game)
+    }
+
+    /// Saves the game, replacing any existing save at the same path.
+    pub fn save<P>(&self, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+        save::save_to_file(self, path)
+    }
+
+    /// Records every command from now on to a replay file, replacing any
+    /// existing file at the same path.
+    ///
+    /// Replays start from the game's seed, so this only works for games
+    /// which have not had any commands yet.
+    pub fn record_to<P>(&mut self, path: P) -> Result<(), ReplayError> where P: AsRef<Path> {
+        self.recorder = Some(Recorder::create(path, self)?);
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Carries out a player command. If it used up the player's turn, the
+    /// game steps on so that everything else gets to take theirs.
     pub fn do_command(&mut self, cmd: Command) {
-        match cmd {
-            Command::Walk(d) => {
-                self.world.walk(d);
-            }
+        if self.world.is_player_dead() { return; }
+
+        // Everything which happens from here on is part of the coming turn
+        log::set_turn(self.turn + 1);
+
+        let failed = match self.recorder {
+            Some(ref mut recorder) => recorder.record(self.turn, cmd).is_err(),
+            None => false,
+        };
+
+        if failed {
+            log::error("Could not record the last command, so recording has stopped.");
+            self.recorder = None;
+        }
+
+        let interact = |done: bool| if done { Some(ActionKind::Interact) } else { None };
+
+        let action = match cmd {
+            Command::Walk(d) => self.world.walk(d, &mut self.rng),
+            Command::PickUp => interact(self.world.pick_up()),
+            Command::Drop(index) => interact(self.world.drop_item(index)),
+            Command::Use(index) => interact(self.world.use_item(index)),
+            Command::Unequip(slot) => interact(self.world.unequip(slot)),
+            Command::Descend => interact(self.world.descend(&mut self.rng)),
+            Command::Ascend => interact(self.world.ascend(&mut self.rng)),
+        };
+
+        if let Some(action) = action {
+            let player = self.world.player();
+            scheduler::spend(&mut self.world.entities, player, action);
+            self.turn += 1;
+            self.turn_taken = true;
+            self.step();
         }
     }
 
+    /// Lets time pass after the player has taken a turn, until they have the
+    /// energy to act again. Every other entity acts as its own energy allows,
+    /// so a fast monster may act several times in one step and a slow one
+    /// not at all. Does nothing if the player has not acted since the last
+    /// step.
+    ///
+    /// `do_command` steps the game itself, so that commands given several
+    /// at a time, as when running or replaying, each get their own turn.
     pub fn step(&mut self) {
+        if !self.turn_taken { return; }
+        self.turn_taken = false;
 
+        systems::remove_dead(&mut self.world);
+
+        loop {
+            let player = self.world.player();
+            let entities = &self.world.entities;
+
+            // A player who is never scheduled could otherwise wait forever
+            if entities.is_dead(player) || !entities.energies.contains(player) || scheduler::is_ready(entities, player) {
+                break;
+            }
+
+            self.time += 1;
+
+            if self.time % TICKS_PER_TURN == 0 {
+                systems::tick_statuses(&mut self.world);
+            }
+
+            systems::gain_energy(&mut self.world);
+            systems::take_turns(&mut self.world, &mut self.rng);
+            systems::remove_dead(&mut self.world);
+        }
     }
 }
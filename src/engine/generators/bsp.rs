@@ -0,0 +1,81 @@
+use engine::{Map, Rng};
+use engine::generators::{Canvas, MapGenerator};
+use util::units::{Point, Rectangle, Size};
+
+use std::cmp;
+
+/// Recursively splits the map in two (binary space partitioning), places a
+/// room in each leaf, then joins sibling partitions with corridors.
+#[derive(Clone, Copy, Debug)]
+pub struct Bsp {
+    /// Partitions are never split into pieces smaller than this
+    pub min_leaf_size: i32,
+}
+
+impl Bsp {
+    pub fn new() -> Bsp {
+        Bsp { min_leaf_size: 8 }
+    }
+
+    /// Fills a partition with rooms, returning a point inside one of them
+    fn split(&self, canvas: &mut Canvas, leaf: Rectangle, rng: &mut Rng) -> Point {
+        // Anything smaller could not hold a room and its surrounding wall
+        let min_leaf_size = cmp::max(self.min_leaf_size, 3);
+        let can_split_x = leaf.width() >= min_leaf_size * 2;
+        let can_split_y = leaf.height() >= min_leaf_size * 2;
+
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => return self.place_room(canvas, leaf, rng),
+            (true, false) => true,
+            (false, true) => false,
+            // Prefer cutting across the longer side to avoid long thin partitions
+            (true, true) => if leaf.width() == leaf.height() { rng.chance(50) } else { leaf.width() > leaf.height() },
+        };
+
+        let loc = leaf.location();
+        let (first, second) = if split_x {
+            let at = rng.range(min_leaf_size, leaf.width() - min_leaf_size + 1);
+            (Rectangle::new(loc, Size::new(at, leaf.height())),
+             Rectangle::new(loc.right(at), Size::new(leaf.width() - at, leaf.height())))
+        } else {
+            let at = rng.range(min_leaf_size, leaf.height() - min_leaf_size + 1);
+            (Rectangle::new(loc, Size::new(leaf.width(), at)),
+             Rectangle::new(loc.down(at), Size::new(leaf.width(), leaf.height() - at)))
+        };
+
+        let a = self.split(canvas, first, rng);
+        let b = self.split(canvas, second, rng);
+        canvas.carve_corridor(a, b, rng);
+
+        if rng.chance(50) { a } else { b }
+    }
+
+    /// Carves a randomly sized room inside a leaf, keeping a wall around it
+    fn place_room(&self, canvas: &mut Canvas, leaf: Rectangle, rng: &mut Rng) -> Point {
+        let max_width = leaf.width() - 2;
+        let max_height = leaf.height() - 2;
+
+        if max_width < 1 || max_height < 1 {
+            return leaf.center();
+        }
+
+        let width = rng.range(cmp::max(max_width / 2, 1), max_width + 1);
+        let height = rng.range(cmp::max(max_height / 2, 1), max_height + 1);
+        let location = leaf.location() + (rng.range(1, leaf.width() - width), rng.range(1, leaf.height() - height));
+        let room = Rectangle::new(location, Size::new(width, height));
+
+        canvas.carve_room(&room);
+        room.center()
+    }
+}
+
+impl MapGenerator for Bsp {
+    fn generate(&self, seed: u64, size: Size) -> Map {
+        let mut rng = Rng::new(seed);
+        let mut canvas = Canvas::new(size);
+
+        self.split(&mut canvas, Rectangle::new(Point::zero(), size), &mut rng);
+
+        canvas.finish(&mut rng)
+    }
+}
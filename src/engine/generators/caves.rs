@@ -0,0 +1,72 @@
+use engine::{Map, Rng};
+use engine::generators::{Canvas, MapGenerator};
+use util::units::{Point, Size};
+
+/// Grows organic caves with a cellular automaton.
+///
+/// The map starts as random noise, then each pass turns a tile into wall if
+/// most of its neighbours are walls and into floor otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct Caves {
+    /// The percentage of tiles which start as walls
+    pub fill_percent: u32,
+    /// The number of smoothing passes
+    pub iterations: u32,
+}
+
+impl Caves {
+    pub fn new() -> Caves {
+        Caves {
+            fill_percent: 45,
+            iterations: 5,
+        }
+    }
+}
+
+impl MapGenerator for Caves {
+    fn generate(&self, seed: u64, size: Size) -> Map {
+        let mut rng = Rng::new(seed);
+        let mut canvas = Canvas::new(size);
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let loc = Point::new(x, y);
+                if !rng.chance(self.fill_percent) {
+                    canvas.carve(loc);
+                }
+            }
+        }
+
+        for _ in 0..self.iterations {
+            let mut next = Canvas::new(size);
+
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let loc = Point::new(x, y);
+                    if canvas.is_interior(loc) && walls_around(&canvas, loc) < 5 {
+                        next.carve(loc);
+                    }
+                }
+            }
+
+            canvas = next;
+        }
+
+        canvas.finish(&mut rng)
+    }
+}
+
+/// Counts the walls in the 3x3 block centred on a point
+fn walls_around(canvas: &Canvas, loc: Point) -> u32 {
+    let mut walls = 0;
+
+    for dy in -1..2 {
+        for dx in -1..2 {
+            if !canvas.is_floor(loc + (dx, dy)) {
+                walls += 1;
+            }
+        }
+    }
+
+    walls
+}
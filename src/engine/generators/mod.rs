@@ -0,0 +1,249 @@
+//! Procedural map generation
+//!
+//! Every generator is deterministic: the same seed and size always produce
+//! the same map. Generated maps are surrounded by walls, have a walkable
+//! starting position, and all of their floor is reachable from it.
+
+use engine::{Map, Rng, Tile};
+use util::units::{Point, Rectangle, Size};
+
+use std::cmp;
+
+mod bsp;
+mod caves;
+mod rooms;
+
+pub use self::bsp::Bsp;
+pub use self::caves::Caves;
+pub use self::rooms::RoomsAndCorridors;
+
+/// Something which can produce a new map
+pub trait MapGenerator {
+    /// Generates a map of the given size.
+    ///
+    /// Maps smaller than 3x3 have no room for anything inside their walls,
+    /// so generators panic if asked for one.
+    fn generate(&self, seed: u64, size: Size) -> Map;
+}
+
+/// A grid of tiles being carved into a map, indexed as `tiles[y][x]`
+struct Canvas {
+    tiles: Vec<Vec<Tile>>,
+    size: Size,
+    floor: Tile,
+}
+
+impl Canvas {
+    /// Creates a canvas of solid wall
+    fn new(size: Size) -> Canvas {
+        assert!(size.x >= 3 && size.y >= 3, "Cannot generate a map smaller than 3x3");
+
+        Canvas {
+            tiles: vec![vec![Tile::wall(); size.x as usize]; size.y as usize],
+            size: size,
+            floor: Tile::floor(),
+        }
+    }
+
+    fn at(&self, loc: Point) -> Tile {
+        self.tiles[loc.y as usize][loc.x as usize]
+    }
+
+    fn set(&mut self, loc: Point, tile: Tile) {
+        self.tiles[loc.y as usize][loc.x as usize] = tile;
+    }
+
+    fn is_floor(&self, loc: Point) -> bool {
+        self.at(loc) == self.floor
+    }
+
+    /// Returns true if the point lies inside the map's outer wall
+    fn is_interior(&self, loc: Point) -> bool {
+        loc.x > 0 && loc.y > 0 && loc.x < self.size.x - 1 && loc.y < self.size.y - 1
+    }
+
+    fn carve(&mut self, loc: Point) {
+        if self.is_interior(loc) {
+            let floor = self.floor;
+            self.set(loc, floor);
+        }
+    }
+
+    fn carve_room(&mut self, room: &Rectangle) {
+        let loc = room.location();
+
+        for y in loc.y..loc.y + room.height() {
+            for x in loc.x..loc.x + room.width() {
+                self.carve(Point::new(x, y));
+            }
+        }
+    }
+
+    /// Carves an L-shaped corridor between two points, randomly choosing
+    /// whether to go horizontally or vertically first.
+    fn carve_corridor(&mut self, from: Point, to: Point, rng: &mut Rng) {
+        let elbow = if rng.chance(50) {
+            Point::new(to.x, from.y)
+        } else {
+            Point::new(from.x, to.y)
+        };
+
+        self.carve_line(from, elbow);
+        self.carve_line(elbow, to);
+    }
+
+    /// Carves a horizontal or vertical line between two points
+    fn carve_line(&mut self, from: Point, to: Point) {
+        for y in cmp::min(from.y, to.y)..cmp::max(from.y, to.y) + 1 {
+            for x in cmp::min(from.x, to.x)..cmp::max(from.x, to.x) + 1 {
+                self.carve(Point::new(x, y));
+            }
+        }
+    }
+
+    /// Finds every connected region of floor, largest first
+    fn regions(&self) -> Vec<Vec<Point>> {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        let mut seen = vec![vec![false; width]; height];
+        let mut regions = vec![];
+
+        for y in 0..height {
+            for x in 0..width {
+                let start = Point::new(x as i32, y as i32);
+                if seen[y][x] || !self.is_floor(start) {
+                    continue;
+                }
+
+                seen[y][x] = true;
+                let mut region = vec![];
+                let mut stack = vec![start];
+
+                while let Some(loc) = stack.pop() {
+                    region.push(loc);
+
+                    for next in &[loc.up(1), loc.down(1), loc.left(1), loc.right(1)] {
+                        let in_bounds = next.x >= 0 && next.y >= 0 && next.x < self.size.x && next.y < self.size.y;
+                        if in_bounds && !seen[next.y as usize][next.x as usize] && self.is_floor(*next) {
+                            seen[next.y as usize][next.x as usize] = true;
+                            stack.push(*next);
+                        }
+                    }
+                }
+
+                region.sort_by_key(|p| (p.y, p.x));
+                regions.push(region);
+            }
+        }
+
+        // Sorting is stable, so equally sized regions keep their scan order
+        regions.sort_by(|a, b| b.len().cmp(&a.len()));
+        regions
+    }
+
+    /// Joins every region of floor to the largest one with corridors, then
+    /// picks a starting position and produces the finished map.
+    fn finish(mut self, rng: &mut Rng) -> Map {
+        loop {
+            let regions = self.regions();
+
+            if regions.is_empty() {
+                let center = Point::new(self.size.x / 2, self.size.y / 2);
+                self.carve(center);
+                continue;
+            }
+
+            if regions.len() == 1 {
+                break;
+            }
+
+            let from = regions[1][0];
+            let to = *regions[0].iter()
+                                .min_by_key(|p| (p.x - from.x).abs() + (p.y - from.y).abs())
+                                .expect("Regions are never empty");
+
+            self.carve_corridor(from, to, rng);
+        }
+
+        let floor = self.regions().remove(0);
+        let starting_position = *rng.choose(&floor).expect("Regions are never empty");
+
+        Map::from_tiles(self.tiles, starting_position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bsp, Caves, MapGenerator, RoomsAndCorridors};
+    use engine::Map;
+    use util::units::{Point, Size};
+
+    static SEEDS: [u64; 5] = [0, 1, 42, 1234, 987654321];
+    static SIZES: [(i32, i32); 5] = [(3, 3), (7, 5), (20, 40), (60, 25), (100, 100)];
+
+    fn generators() -> Vec<(&'static str, Box<MapGenerator>)> {
+        vec![
+            ("rooms and corridors", Box::new(RoomsAndCorridors::new())),
+            ("caves", Box::new(Caves::new())),
+            ("bsp", Box::new(Bsp::new())),
+        ]
+    }
+
+    /// Every walkable point which can be reached from the starting position
+    /// by steps up, down, left and right
+    fn reachable(map: &Map) -> Vec<Vec<bool>> {
+        let mut seen = vec![vec![false; map.width() as usize]; map.height() as usize];
+        let mut stack = vec![map.starting_position];
+        seen[map.starting_position.y as usize][map.starting_position.x as usize] = true;
+
+        while let Some(loc) = stack.pop() {
+            for &next in &[loc.up(1), loc.down(1), loc.left(1), loc.right(1)] {
+                if map.in_bounds(next) && map.is_walkable(next) && !seen[next.y as usize][next.x as usize] {
+                    seen[next.y as usize][next.x as usize] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        seen
+    }
+
+    #[test]
+    fn every_floor_tile_is_reachable_from_the_start() {
+        for (name, generator) in generators() {
+            for &seed in SEEDS.iter() {
+                for &(width, height) in SIZES.iter() {
+                    let map = generator.generate(seed, Size::new(width, height));
+                    let case = format!("{} with seed {} at {}x{}", name, seed, width, height);
+
+                    assert_eq!((map.width(), map.height()), (width, height), "{} is the wrong size", case);
+                    assert!(map.is_walkable(map.starting_position), "{} starts in a wall", case);
+
+                    let seen = reachable(&map);
+
+                    for y in 0..height {
+                        for x in 0..width {
+                            let loc = Point::new(x, y);
+                            let edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+                            assert!(!(edge && map.is_walkable(loc)),
+                                    "{} has floor in its outer wall at {},{}", case, x, y);
+                            assert!(!map.is_walkable(loc) || seen[y as usize][x as usize],
+                                    "{} cannot reach {},{} from the start", case, x, y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_map() {
+        for (name, generator) in generators() {
+            let first = generator.generate(42, Size::new(60, 25));
+            let second = generator.generate(42, Size::new(60, 25));
+
+            assert!(first.tiles == second.tiles, "{} is not deterministic", name);
+            assert_eq!(first.starting_position, second.starting_position, "{} is not deterministic", name);
+        }
+    }
+}
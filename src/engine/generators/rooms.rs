@@ -0,0 +1,62 @@
+use engine::{Map, Rng};
+use engine::generators::{Canvas, MapGenerator};
+use util::units::{Point, Rectangle, Size};
+
+use std::cmp;
+
+/// Scatters rectangular rooms across the map and joins each one to the
+/// previous room with a corridor.
+#[derive(Clone, Copy, Debug)]
+pub struct RoomsAndCorridors {
+    /// The number of attempts made to place a room
+    pub max_rooms: u32,
+    /// The smallest width or height of a room
+    pub min_room_size: i32,
+    /// The largest width or height of a room
+    pub max_room_size: i32,
+}
+
+impl RoomsAndCorridors {
+    pub fn new() -> RoomsAndCorridors {
+        RoomsAndCorridors {
+            max_rooms: 30,
+            min_room_size: 4,
+            max_room_size: 10,
+        }
+    }
+}
+
+impl MapGenerator for RoomsAndCorridors {
+    fn generate(&self, seed: u64, size: Size) -> Map {
+        let mut rng = Rng::new(seed);
+        let mut canvas = Canvas::new(size);
+        let mut rooms: Vec<Rectangle> = vec![];
+
+        for _ in 0..self.max_rooms {
+            // Rooms must leave space for the outer wall on every side
+            let max_width = cmp::min(size.x - 2, self.max_room_size);
+            let max_height = cmp::min(size.y - 2, self.max_room_size);
+            let width = rng.range(cmp::min(self.min_room_size, max_width), max_width + 1);
+            let height = rng.range(cmp::min(self.min_room_size, max_height), max_height + 1);
+
+            let location = Point::new(rng.range(1, size.x - width), rng.range(1, size.y - height));
+            let room = Rectangle::new(location, Size::new(width, height));
+
+            // Grow the room by one tile when checking so that rooms never share a wall
+            let padded = Rectangle::new(location - (1, 1), Size::new(width + 2, height + 2));
+            if rooms.iter().any(|other| padded.intersects(other)) {
+                continue;
+            }
+
+            canvas.carve_room(&room);
+
+            if let Some(previous) = rooms.last() {
+                canvas.carve_corridor(previous.center(), room.center(), &mut rng);
+            }
+
+            rooms.push(room);
+        }
+
+        canvas.finish(&mut rng)
+    }
+}
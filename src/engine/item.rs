@@ -0,0 +1,123 @@
+use engine::status::Status;
+use util::units::Color;
+
+/// What an item does when it is used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemKind {
+    /// Restores some health, and is used up in the process
+    Potion { heal: i32 },
+    /// Puts the drinker under a status effect, and is used up in the process
+    Elixir { status: Status },
+    /// Valuable, but of no practical use
+    Treasure,
+    /// Can be worn in a slot, changing the wearer's stats while it is
+    Equipment { slot: Slot, modifiers: Modifiers },
+}
+
+/// Where on the body a piece of equipment is worn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Weapon,
+    Armour,
+    Ring,
+}
+
+impl Slot {
+    /// Returns every slot, in the order they are shown
+    pub fn all() -> &'static [Slot; 3] {
+        static SLOTS: [Slot; 3] = [Slot::Weapon, Slot::Armour, Slot::Ring];
+        &SLOTS
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Slot::Weapon => "Weapon",
+            Slot::Armour => "Armour",
+            Slot::Ring => "Ring",
+        }
+    }
+
+    /// Returns the slot's position in `Slot::all`
+    pub fn index(&self) -> usize {
+        match *self {
+            Slot::Weapon => 0,
+            Slot::Armour => 1,
+            Slot::Ring => 2,
+        }
+    }
+}
+
+/// Amounts added to an actor's stats
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub attack: i32,
+    pub defence: i32,
+    pub max_health: i32,
+}
+
+impl Modifiers {
+    pub fn new(attack: i32, defence: i32, max_health: i32) -> Modifiers {
+        Modifiers { attack: attack, defence: defence, max_health: max_health }
+    }
+}
+
+/// Something which can lie on the ground or be carried by an actor
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    name: String,
+    glyph: char,
+    color: Color,
+    kind: ItemKind,
+}
+
+impl Item {
+    pub fn new(name: &str, glyph: char, color: Color, kind: ItemKind) -> Item {
+        Item {
+            name: name.to_string(),
+            glyph: glyph,
+            color: color,
+            kind: kind,
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn glyph(&self) -> char { self.glyph }
+    pub fn color(&self) -> Color { self.color }
+    pub fn kind(&self) -> ItemKind { self.kind }
+
+    /// Returns the slot the item is worn in, if it can be worn
+    pub fn slot(&self) -> Option<Slot> {
+        match self.kind {
+            ItemKind::Equipment { slot, .. } => Some(slot),
+            _ => None,
+        }
+    }
+
+    /// Returns how the item changes its wearer's stats
+    pub fn modifiers(&self) -> Modifiers {
+        match self.kind {
+            ItemKind::Equipment { modifiers, .. } => modifiers,
+            _ => Modifiers::default(),
+        }
+    }
+
+    /// Describes what the item does
+    pub fn description(&self) -> String {
+        match self.kind {
+            ItemKind::Potion { heal } => format!("Restores {} health.", heal),
+            ItemKind::Elixir { status } => format!("Causes {} for {} turns.", status.kind.name().to_lowercase(), status.turns),
+            ItemKind::Treasure => "Worth a small fortune.".to_string(),
+            ItemKind::Equipment { slot, modifiers } => {
+                let bonuses: Vec<String> = [(modifiers.attack, "attack"),
+                                            (modifiers.defence, "defence"),
+                                            (modifiers.max_health, "max health")]
+                    .iter()
+                    .filter(|&&(amount, _)| amount != 0)
+                    .map(|&(amount, stat)| format!("{:+} {}", amount, stat))
+                    .collect();
+
+                format!("{}: {}.", slot.name(), bonuses.join(", "))
+            }
+        }
+    }
+}
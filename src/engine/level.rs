@@ -0,0 +1,89 @@
+use engine::{Map, MapGenerator, Rng, Tile};
+use engine::ecs::Ecs;
+use engine::generators::RoomsAndCorridors;
+use util::units::{Point, Size};
+
+/// The number of monsters placed on a newly generated level
+pub const MONSTER_COUNT: usize = 30;
+
+/// The number of items placed on a newly generated level
+pub const ITEM_COUNT: usize = 20;
+
+/// The size of every generated level
+const LEVEL_SIZE: (i32, i32) = (100, 100);
+
+/// A level of the dungeon which the player is not currently on
+pub struct Level {
+    pub map: Map,
+    pub entities: Ecs,
+}
+
+/// Generates the map for a level of the dungeon.
+///
+/// Every level has stairs down somewhere far from its starting position.
+/// Levels below the first also have stairs up at their starting position.
+pub fn generate_map(depth: u32, rng: &mut Rng) -> Map {
+    let (width, height) = LEVEL_SIZE;
+    let mut map = RoomsAndCorridors::new().generate(rng.next_u64(), Size::new(width, height));
+    let start = map.starting_position;
+
+    if depth > 1 {
+        map.set_tile(start, Tile::stairs_up());
+    }
+
+    let mut floor = vec![];
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let loc = Point::new(x, y);
+            if loc != start && map.is_walkable(loc) {
+                floor.push(loc);
+            }
+        }
+    }
+
+    // Pick somewhere in the farther half of the level, so reaching the
+    // stairs takes some exploring
+    floor.sort_by_key(|p| -((p.x - start.x).abs() + (p.y - start.y).abs()));
+    let farther_half = &floor[..(floor.len() + 1) / 2];
+
+    if let Some(&stairs) = rng.choose(farther_half) {
+        map.set_tile(stairs, Tile::stairs_down());
+    }
+
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_map;
+    use engine::{Rng, Tile};
+
+    #[test]
+    fn same_seed_gives_same_dungeon() {
+        let first = generate_map(1, &mut Rng::new(1234));
+        let second = generate_map(1, &mut Rng::new(1234));
+
+        assert!(first.tiles == second.tiles);
+        assert_eq!(first.starting_position, second.starting_position);
+    }
+
+    #[test]
+    fn different_seeds_give_different_dungeons() {
+        let first = generate_map(1, &mut Rng::new(1234));
+        let second = generate_map(1, &mut Rng::new(4321));
+
+        assert!(first.tiles != second.tiles);
+    }
+
+    #[test]
+    fn only_deeper_levels_have_stairs_up() {
+        let first = generate_map(1, &mut Rng::new(5));
+        let second = generate_map(2, &mut Rng::new(5));
+
+        assert!(first.find_tile(Tile::stairs_down()).is_some());
+        assert_eq!(first.find_tile(Tile::stairs_up()), None);
+
+        assert!(second.find_tile(Tile::stairs_down()).is_some());
+        assert_eq!(second.find_tile(Tile::stairs_up()), Some(second.starting_position));
+    }
+}
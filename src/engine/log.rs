@@ -1,55 +1,531 @@
-use std::string::String;
-use std::slice::Iter;
+//! The message log shown beneath the map
+//!
+//! Every message belongs to a `Category`, which the player can hide, and is
+//! stamped with the turn it was written on. Only the newest `CAPACITY`
+//! messages are kept, and a message repeating the one before it is counted
+//! rather than stored again, so that it can be shown as "You hit the Rat x3".
+//! Only messages which say exactly the same thing are counted together, so
+//! hits for different amounts of damage are each kept.
+//!
+//! Text may colour parts of itself with spans written as `{colour:text}`,
+//! where the colour is one of the names in `COLOURS` or `#rrggbb`. Anything
+//! else in braces is left as it is, and `{{` and `}}` stand for `{` and `}`.
+//! `coloured` writes a span for any colour, and `escape` keeps any other
+//! text, such as a name, from being read as a span.
+
+use util::units::Color;
+
 use std::cell::RefCell;
+use std::collections::vec_deque::{self, VecDeque};
+use std::iter::Rev;
 
 thread_local!(pub static LOG: RefCell<MessageList> = RefCell::new(MessageList::new()));
 
-pub fn info(text: &str) {
-    LOG.with(|w| w.borrow_mut().info(text));
+/// The most messages kept before the oldest are forgotten
+pub const CAPACITY: usize = 200;
+
+/// Colours which can be used by name in spans
+static COLOURS: [(&'static str, (u8, u8, u8)); 8] = [
+    ("red", (220, 50, 50)),
+    ("green", (80, 200, 80)),
+    ("blue", (90, 130, 230)),
+    ("yellow", (230, 210, 60)),
+    ("orange", (230, 140, 40)),
+    ("purple", (190, 100, 220)),
+    ("grey", (128, 128, 128)),
+    ("white", (255, 255, 255)),
+];
+
+pub fn add(category: Category, text: &str) {
+    LOG.with(|w| w.borrow_mut().add(category, text));
 }
 
+pub fn combat(text: &str) {
+    add(Category::Combat, text);
+}
+
+pub fn loot(text: &str) {
+    add(Category::Loot, text);
+}
+
+pub fn system(text: &str) {
+    add(Category::System, text);
+}
+
+pub fn debug(text: &str) {
+    add(Category::Debug, text);
+}
+
+pub fn notice(text: &str) {
+    add(Category::Notice, text);
+}
+
+/// Reports something going wrong, such as a failed save, in red
 pub fn error(text: &str) {
-    LOG.with(|w| w.borrow_mut().error(text));
+    add(Category::System, &format!("{{red:{}}}", escape(text)));
+}
+
+/// Replaces every message, as when loading a game. Which categories are
+/// shown is up to the player rather than the messages, so it is kept.
+pub fn replace(mut messages: MessageList) {
+    LOG.with(|l| {
+        let mut log = l.borrow_mut();
+        messages.filter = log.filter;
+        *log = messages;
+    });
+}
+
+/// Sets the turn new messages are stamped with
+pub fn set_turn(turn: u32) {
+    LOG.with(|w| w.borrow_mut().set_turn(turn));
+}
+
+/// Wraps text in a span of the given colour
+pub fn coloured(text: &str, color: Color) -> String {
+    format!("{{#{:02x}{:02x}{:02x}:{}}}", color.r, color.g, color.b, escape(text))
+}
+
+/// Doubles every brace, so that the text reads as itself wherever it is
+/// put in a message
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+/// What a message is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// Fighting, dying and status effects
+    Combat,
+    /// Picking up, using and wearing items
+    Loot,
+    /// Travelling between levels, saving and anything going wrong
+    System,
+    /// Details only of interest when working on the game. Hidden unless
+    /// asked for.
+    Debug,
+    /// Answers to the player changing how the game behaves, such as hiding
+    /// messages. Always shown.
+    Notice,
+}
+
+static CATEGORIES: [Category; 5] = [
+    Category::Combat,
+    Category::Loot,
+    Category::System,
+    Category::Debug,
+    Category::Notice,
+];
+
+impl Category {
+    pub fn all() -> &'static [Category; 5] {
+        &CATEGORIES
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Category::Combat => "combat",
+            Category::Loot => "loot",
+            Category::System => "system",
+            Category::Debug => "debug",
+            Category::Notice => "notice",
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Category> {
+        CATEGORIES.iter().find(|c| c.name() == name).cloned()
+    }
+
+    /// The colour of any text in a message not inside a span
+    pub fn color(&self) -> Color {
+        match *self {
+            Category::Combat => Color::new(255, 255, 255),
+            Category::Loot => Color::new(200, 220, 255),
+            Category::System => Color::new(170, 170, 170),
+            Category::Debug => Color::new(100, 100, 100),
+            Category::Notice => Color::new(230, 210, 60),
+        }
+    }
+
+    fn index(&self) -> usize {
+        match *self {
+            Category::Combat => 0,
+            Category::Loot => 1,
+            Category::System => 2,
+            Category::Debug => 3,
+            Category::Notice => 4,
+        }
+    }
 }
 
-pub enum MessageType {
-    Info,
-    Error,
+/// A run of text in one colour, or in the message's own colour if `None`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Message {
-    text: String,
-    ty: MessageType,
+    markup: String,
+    spans: Vec<Span>,
+    category: Category,
+    turn: u32,
+    /// How many times in a row the message was written
+    count: u32,
 }
 
 impl Message {
-    pub fn text(&self) -> &str {
-        &self.text
+    pub fn new(category: Category, turn: u32, markup: &str) -> Message {
+        Message {
+            markup: markup.to_string(),
+            spans: parse(markup),
+            category: category,
+            turn: turn,
+            count: 1,
+        }
+    }
+
+    /// The message without its colour spans
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// The message as it was written, colour spans and all
+    pub fn markup(&self) -> &str {
+        &self.markup
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// The message's spans, followed by how many times it was written in
+    /// grey if it was more than once
+    pub fn spans_with_count(&self) -> Vec<Span> {
+        let mut spans = self.spans.clone();
+
+        if self.count > 1 {
+            spans.push(Span { text: format!(" x{}", self.count), color: Some(Color::new(128, 128, 128)) });
+        }
+
+        spans
+    }
+
+    pub fn category(&self) -> Category {
+        self.category
+    }
+
+    /// The turn the message was last written on
+    pub fn turn(&self) -> u32 {
+        self.turn
     }
 
-    pub fn ty(&self) -> &MessageType {
-        &self.ty
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Sets how many times in a row the message was written, as when loading
+    pub fn with_count(mut self, count: u32) -> Message {
+        self.count = count;
+        self
+    }
+}
+
+/// Which categories of message are shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Filter {
+    shown: [bool; 5],
+}
+
+impl Filter {
+    pub fn shows(&self, category: Category) -> bool {
+        self.shown[category.index()]
+    }
+
+    /// Shows or hides a category. Notices cannot be hidden.
+    pub fn set(&mut self, category: Category, shown: bool) {
+        if category != Category::Notice {
+            self.shown[category.index()] = shown;
+        }
+    }
+
+    /// Shows a hidden category or hides a shown one, returning whether it
+    /// is now shown
+    pub fn toggle(&mut self, category: Category) -> bool {
+        let shown = !self.shows(category);
+        self.set(category, shown);
+        self.shows(category)
+    }
+}
+
+impl Default for Filter {
+    /// Shows everything but debug messages
+    fn default() -> Filter {
+        Filter { shown: [true, true, true, false, true] }
     }
 }
 
 pub struct MessageList {
-    messages: Vec<Message>,
+    /// Oldest first
+    messages: VecDeque<Message>,
+    /// The turn new messages are stamped with
+    turn: u32,
+    pub filter: Filter,
 }
 
 impl MessageList {
     pub fn new() -> MessageList {
-        MessageList { messages: vec![] }
+        MessageList { messages: VecDeque::with_capacity(CAPACITY), turn: 0, filter: Filter::default() }
+    }
+
+    /// Writes a message, counting it against the newest one instead if it
+    /// says the same thing
+    pub fn add(&mut self, category: Category, text: &str) {
+        if let Some(newest) = self.messages.back_mut() {
+            if newest.category == category && newest.markup == text {
+                newest.count += 1;
+                newest.turn = self.turn;
+                return;
+            }
+        }
+
+        let message = Message::new(category, self.turn, text);
+        self.push(message);
+    }
+
+    /// Adds a message as it is, without counting it against the newest one
+    pub fn push(&mut self, message: Message) {
+        if self.messages.len() >= CAPACITY {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(message);
+    }
+
+    pub fn set_turn(&mut self, turn: u32) {
+        self.turn = turn;
+    }
+
+    /// Every message, newest first
+    pub fn items(&self) -> Rev<vec_deque::Iter<'_, Message>> {
+        self.messages.iter().rev()
+    }
+
+    /// The messages in categories the filter shows, newest first
+    pub fn visible<'a>(&'a self) -> Box<Iterator<Item = &'a Message> + 'a> {
+        let filter = self.filter;
+        Box::new(self.items().filter(move |m| filter.shows(m.category)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// Breaks spans into lines no wider than `width`, at spaces where possible.
+/// Words too long for a line of their own are split wherever they reach
+/// the edge. Spaces at a break are dropped.
+pub fn wrap(spans: &[Span], width: usize) -> Vec<Vec<Span>> {
+    let chars: Vec<(char, Option<Color>)> = spans.iter()
+        .flat_map(|s| s.text.chars().map(move |c| (c, s.color)))
+        .collect();
+
+    let mut lines = vec![];
+    let mut start = 0;
+
+    while start < chars.len() && width > 0 {
+        if start > 0 {
+            while start < chars.len() && chars[start].0 == ' ' {
+                start += 1;
+            }
+
+            if start == chars.len() {
+                break;
+            }
+        }
+
+        let end = if chars.len() - start <= width {
+            chars.len()
+        } else {
+            (start + 1..start + width + 1).rev().find(|&i| chars[i].0 == ' ').unwrap_or(start + width)
+        };
+
+        lines.push(join(&chars[start..end]));
+        start = end;
+    }
+
+    if lines.is_empty() {
+        lines.push(vec![]);
+    }
+
+    lines
+}
+
+/// Gathers runs of characters of the same colour back into spans
+fn join(chars: &[(char, Option<Color>)]) -> Vec<Span> {
+    let mut spans: Vec<Span> = vec![];
+
+    for &(c, color) in chars {
+        match spans.last_mut() {
+            Some(ref mut span) if span.color == color => {
+                span.text.push(c);
+                continue;
+            }
+            _ => {}
+        }
+
+        spans.push(Span { text: c.to_string(), color: color });
+    }
+
+    spans
+}
+
+/// Splits text into spans at every `{colour:text}`
+fn parse(markup: &str) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut rest = markup;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            plain.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+
+        if c == '{' {
+            if let Some((span, length)) = parse_span(&rest[1..]) {
+                if !plain.is_empty() {
+                    spans.push(Span { text: plain.clone(), color: None });
+                    plain.clear();
+                }
+
+                spans.push(span);
+                rest = &rest[1 + length..];
+                continue;
+            }
+        }
+
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
     }
 
-    pub fn info(&mut self, text: &str) {
-        self.messages.insert(0, Message { text: text.to_string(), ty: MessageType::Info });
+    if !plain.is_empty() {
+        spans.push(Span { text: plain, color: None });
     }
 
-    pub fn error(&mut self, text: &str) {
-        self.messages.insert(0, Message { text: text.to_string(), ty: MessageType::Error });
+    spans
+}
+
+/// Reads a span from just after its opening brace, returning it along with
+/// how much of the text it took up
+fn parse_span(markup: &str) -> Option<(Span, usize)> {
+    let colon = markup.find(':')?;
+    let name = &markup[..colon];
+
+    if name.contains('{') || name.contains('}') {
+        return None;
     }
 
-    pub fn items(&self) -> Iter<Message> {
-        self.messages.iter()
+    let color = parse_color(name)?;
+    let mut text = String::new();
+    let mut rest = &markup[colon + 1..];
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push(c);
+            rest = &rest[2..];
+        } else if c == '}' {
+            let length = markup.len() - rest.len() + 1;
+            return Some((Span { text: text, color: Some(color) }, length));
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    None
+}
+
+fn parse_color(name: &str) -> Option<Color> {
+    // Checked first so that the channels can be sliced by byte
+    if name.starts_with('#') && name.len() == 7 && name.is_ascii() {
+        let channel = |i: usize| u8::from_str_radix(&name[i..i + 2], 16).ok();
+        return Some(Color::new(channel(1)?, channel(3)?, channel(5)?));
+    }
+
+    COLOURS.iter().find(|&&(n, _)| n == name).map(|&(_, (r, g, b))| Color::new(r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coloured, escape, parse, Category, MessageList, Span};
+    use util::units::Color;
+
+    fn plain(text: &str) -> Span {
+        Span { text: text.to_string(), color: None }
+    }
+
+    #[test]
+    fn parses_spans() {
+        assert_eq!(parse("You feel {red:watched}."), vec![
+            plain("You feel "),
+            Span { text: "watched".to_string(), color: Some(Color::new(220, 50, 50)) },
+            plain("."),
+        ]);
+        assert_eq!(parse("{#0a0B0c:x}"), vec![Span { text: "x".to_string(), color: Some(Color::new(10, 11, 12)) }]);
+        assert_eq!(parse("{nothing:here} {red:unfinished"), vec![plain("{nothing:here} {red:unfinished")]);
+    }
+
+    #[test]
+    fn ignores_colours_which_are_not_ascii() {
+        assert_eq!(parse("{#aé12c:x}"), vec![plain("{#aé12c:x}")]);
+        assert_eq!(parse("{#é:x}"), vec![plain("{#é:x}")]);
+    }
+
+    #[test]
+    fn escaped_text_reads_as_itself() {
+        let name = "Sword {red:of} Doom: }{";
+        let blue = Color::new(0, 0, 255);
+
+        assert_eq!(parse(&escape(name)), vec![plain(name)]);
+        assert_eq!(parse(&format!("The {} dies.", escape(name))), vec![plain(&format!("The {} dies.", name))]);
+        assert_eq!(parse(&coloured(name, blue)), vec![Span { text: name.to_string(), color: Some(blue) }]);
+    }
+
+    #[test]
+    fn collapses_only_identical_repeats() {
+        let mut log = MessageList::new();
+
+        log.add(Category::Combat, "You hit the rat for 3 damage.");
+        log.add(Category::Combat, "You hit the rat for 3 damage.");
+        log.add(Category::Combat, "You hit the rat for 12 damage.");
+        log.add(Category::Loot, "You hit the rat for 12 damage.");
+        log.set_turn(4);
+        log.add(Category::Loot, "You hit the rat for 12 damage.");
+
+        let messages: Vec<_> = log.items().map(|m| (m.text(), m.count(), m.turn())).collect();
+        assert_eq!(messages, vec![
+            ("You hit the rat for 12 damage.".to_string(), 2, 4),
+            ("You hit the rat for 12 damage.".to_string(), 1, 0),
+            ("You hit the rat for 3 damage.".to_string(), 2, 0),
+        ]);
+    }
+
+    #[test]
+    fn notices_cannot_be_hidden() {
+        let mut log = MessageList::new();
+        log.add(Category::Notice, "Hiding everything.");
+
+        for &category in Category::all().iter() {
+            log.filter.set(category, false);
+        }
+
+        assert!(log.filter.toggle(Category::Notice));
+        assert_eq!(log.visible().count(), 1);
     }
 }
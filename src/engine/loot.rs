@@ -0,0 +1,115 @@
+use engine::{Item, ItemKind, Modifiers, Rng, Slot};
+use engine::status::{Status, StatusKind};
+use util::units::Color;
+
+/// A kind of item which can be placed in the world
+struct Loot {
+    name: &'static str,
+    glyph: char,
+    color: (u8, u8, u8),
+    kind: ItemKind,
+    /// How often the item appears, relative to the others
+    weight: u32,
+}
+
+static LOOT: [Loot; 13] = [
+    Loot {
+        name: "Healing Potion",
+        glyph: '!',
+        color: (200, 40, 60),
+        kind: ItemKind::Potion { heal: 15 },
+        weight: 30,
+    },
+    Loot {
+        name: "Greater Healing Potion",
+        glyph: '!',
+        color: (240, 80, 200),
+        kind: ItemKind::Potion { heal: 40 },
+        weight: 10,
+    },
+    Loot {
+        name: "Potion of Speed",
+        glyph: '!',
+        color: (230, 210, 60),
+        kind: ItemKind::Elixir { status: Status { kind: StatusKind::Haste, turns: 20, strength: 1 } },
+        weight: 6,
+    },
+    Loot {
+        name: "Potion of Regeneration",
+        glyph: '!',
+        color: (240, 120, 120),
+        kind: ItemKind::Elixir { status: Status { kind: StatusKind::Regeneration, turns: 10, strength: 2 } },
+        weight: 6,
+    },
+    Loot {
+        name: "Murky Potion",
+        glyph: '!',
+        color: (110, 90, 120),
+        kind: ItemKind::Elixir { status: Status { kind: StatusKind::Confusion, turns: 8, strength: 1 } },
+        weight: 4,
+    },
+    Loot {
+        name: "Gold Coins",
+        glyph: '$',
+        color: (230, 200, 50),
+        kind: ItemKind::Treasure,
+        weight: 25,
+    },
+    Loot {
+        name: "Ruby",
+        glyph: '*',
+        color: (220, 30, 30),
+        kind: ItemKind::Treasure,
+        weight: 10,
+    },
+    Loot {
+        name: "Dagger",
+        glyph: '/',
+        color: (180, 180, 200),
+        kind: ItemKind::Equipment { slot: Slot::Weapon, modifiers: Modifiers { attack: 2, defence: 0, max_health: 0 } },
+        weight: 8,
+    },
+    Loot {
+        name: "Longsword",
+        glyph: '/',
+        color: (140, 170, 230),
+        kind: ItemKind::Equipment { slot: Slot::Weapon, modifiers: Modifiers { attack: 5, defence: 0, max_health: 0 } },
+        weight: 4,
+    },
+    Loot {
+        name: "Leather Armour",
+        glyph: '[',
+        color: (150, 100, 50),
+        kind: ItemKind::Equipment { slot: Slot::Armour, modifiers: Modifiers { attack: 0, defence: 2, max_health: 0 } },
+        weight: 8,
+    },
+    Loot {
+        name: "Chain Mail",
+        glyph: '[',
+        color: (170, 170, 170),
+        kind: ItemKind::Equipment { slot: Slot::Armour, modifiers: Modifiers { attack: -1, defence: 4, max_health: 0 } },
+        weight: 4,
+    },
+    Loot {
+        name: "Ring of Vitality",
+        glyph: '=',
+        color: (80, 220, 120),
+        kind: ItemKind::Equipment { slot: Slot::Ring, modifiers: Modifiers { attack: 0, defence: 0, max_health: 25 } },
+        weight: 3,
+    },
+    Loot {
+        name: "Ring of Might",
+        glyph: '=',
+        color: (230, 120, 40),
+        kind: ItemKind::Equipment { slot: Slot::Ring, modifiers: Modifiers { attack: 2, defence: 1, max_health: 0 } },
+        weight: 3,
+    },
+];
+
+/// Creates a randomly chosen item
+pub fn random_item(rng: &mut Rng) -> Item {
+    let loot = rng.choose_weighted(&LOOT, |l| l.weight).expect("No loot defined");
+    let (r, g, b) = loot.color;
+
+    Item::new(loot.name, loot.glyph, Color::new(r, g, b), loot.kind)
+}
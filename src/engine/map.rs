@@ -1,23 +1,33 @@
-use engine::Tile;
+use engine::{Item, Tile};
+use engine::fov;
 use util::FromChar;
 use util::units::{Point, Size};
 
+use std::cmp;
+use std::error::Error;
+use std::fmt;
 use std::path::Path;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read};
 
 /// A map.
 pub struct Map {
     pub tiles: Vec<Vec<Tile>>,
     pub size: Size,
     pub starting_position: Point,
+    /// Tiles in the player's current field of view
+    visible: Vec<Vec<bool>>,
+    /// Tiles the player has seen at some point
+    explored: Vec<Vec<bool>>,
+    /// Items lying on each tile, topmost last
+    items: Vec<Vec<Vec<Item>>>,
 }
 
 impl Map {
     pub fn new() -> Map {
-        let mut tiles: Vec<Vec<Tile>> = vec!(vec!(Tile::Grass; 100); 100);
+        let wall = Tile::wall();
+        let mut tiles: Vec<Vec<Tile>> = vec!(vec!(Tile::named("grass").unwrap_or(Tile::floor()); 100); 100);
         let starting_position = Point::new(5, 5);
-        let size = Size::new(100, 100);
 
 
         for x in 0..tiles.len() {
@@ -26,62 +36,100 @@ impl Map {
                 || y == 0 || x == 0
                 || y == tiles[x].len() - 1 || x == tiles.len() - 1;
                 if success {
-                    tiles[x][y] = Tile::Wall
+                    tiles[x][y] = wall
                 }
             }
         }
 
+        Map::from_tiles(tiles, starting_position)
+    }
+
+    /// Creates a new map from rows of tiles, none of which have been seen yet.
+    pub fn from_tiles(tiles: Vec<Vec<Tile>>, starting_position: Point) -> Map {
+        let height = tiles.len();
+        let width = tiles.first().map_or(0, |row| row.len());
+
         Map {
             tiles: tiles,
-            size: size,
+            size: Size::new(width as i32, height as i32),
             starting_position: starting_position,
+            visible: vec![vec![false; width]; height],
+            explored: vec![vec![false; width]; height],
+            items: vec![vec![vec![]; width]; height],
         }
     }
 
     /// Creates a new map from a string.
-    pub fn from_string(s: String) -> Map {
-        let lines: Vec<&str> = s.split('\n')
-                                   .filter(|l| !l.is_empty())
-                                   .collect();
+    ///
+    /// Blank lines are ignored, but every other line must be the same width
+    /// and the map must contain exactly one starting position, `@`.
+    pub fn from_string(s: String) -> Result<Map, MapError> {
+        let lines: Vec<(usize, &str)> = s.split('\n')
+                                         .map(|l| l.trim_end_matches('\r'))
+                                         .enumerate()
+                                         .map(|(i, l)| (i + 1, l))
+                                         .filter(|&(_, l)| !l.is_empty())
+                                         .collect();
 
-        let expected_line_length = lines.first().expect("Map string contained no lines").len();
+        let expected_line_length = match lines.first() {
+            Some(&(_, l)) => l.chars().count(),
+            None => return Err(MapError::Empty),
+        };
 
-        if !lines.iter().all(|x| x.len() == expected_line_length) {
-            panic!("Different length lines in level string")
-        }
+        let mut tiles: Vec<Vec<Tile>> = Vec::with_capacity(lines.len());
+        let mut starting_position = None;
 
-        let tiles: Vec<Vec<Tile>> = lines.iter()
-                                         .map(|l| l.chars()
-                                                   .map(|c| Tile::from_char(c))
-                                                   .collect())
-                                         .collect();
+        for (y, &(line, row)) in lines.iter().enumerate() {
+            let found = row.chars().count();
+            if found != expected_line_length {
+                return Err(MapError::RaggedLine {
+                    line: line,
+                    expected: expected_line_length,
+                    found: found,
+                });
+            }
 
-        let mut starting_position = None;
+            let mut row_tiles = Vec::with_capacity(found);
 
-        for (y, row_vec) in lines.iter().enumerate() {
-            for (x, tile) in row_vec.chars().enumerate() {
-                if tile == '@' {
+            for (x, c) in row.chars().enumerate() {
+                // The starting position is always on an ordinary floor tile
+                let tile = if c == '@' { Ok(Tile::floor()) } else { Tile::from_char(c) };
+                let tile = tile.map_err(|_| MapError::UnknownTile {
+                    line: line,
+                    column: x + 1,
+                    c: c,
+                })?;
+
+                if c == '@' {
+                    if starting_position.is_some() {
+                        return Err(MapError::DuplicateStartingPosition { line: line, column: x + 1 });
+                    }
                     starting_position = Some(Point::new(x as i32, y as i32));
                 }
+
+                row_tiles.push(tile);
             }
-        }
 
-        let starting_position = starting_position.expect("Map string did not contain a starting position, '@'");
+            tiles.push(row_tiles);
+        }
 
-        let size = Size::new(tiles.len() as i32, tiles[0].len() as i32);
+        let starting_position = match starting_position {
+            Some(pos) => pos,
+            None => {
+                let &(line, row) = lines.last().expect("Map has at least one line");
+                return Err(MapError::NoStartingPosition { line: line, column: row.chars().count() + 1 });
+            }
+        };
 
-        Map {
-            tiles: tiles,
-            size: size,
-            starting_position: starting_position,
-        }
+        Ok(Map::from_tiles(tiles, starting_position))
     }
 
-    pub fn from_file<T>(path: T) -> Map where T: AsRef<Path> {
-        let mut level_file = File::open(path).ok().expect("Could not find level file");
+    /// Creates a new map from the contents of a file.
+    pub fn from_file<T>(path: T) -> Result<Map, MapError> where T: AsRef<Path> {
+        let mut level_file = File::open(path)?;
 
         let mut level_string = String::new();
-        level_file.read_to_string(&mut level_string).ok().expect("Could not read from level file");
+        level_file.read_to_string(&mut level_string)?;
 
         Map::from_string(level_string)
     }
@@ -94,6 +142,93 @@ impl Map {
         self.at(loc).is_walkable()
     }
 
+    pub fn is_transparent(&self, loc: Point) -> bool {
+        self.at(loc).is_transparent()
+    }
+
+    /// Returns true if the point lies within the map
+    pub fn in_bounds(&self, loc: Point) -> bool {
+        loc.x >= 0 && loc.y >= 0 && loc.x < self.width() && loc.y < self.height()
+    }
+
+    /// Returns true if the tile is in the player's current field of view
+    pub fn is_visible(&self, loc: Point) -> bool {
+        self.in_bounds(loc) && self.visible[loc.y as usize][loc.x as usize]
+    }
+
+    /// Returns true if the player has ever seen the tile
+    pub fn is_explored(&self, loc: Point) -> bool {
+        self.in_bounds(loc) && self.explored[loc.y as usize][loc.x as usize]
+    }
+
+    /// Marks whether the player has seen a tile
+    pub fn set_explored(&mut self, loc: Point, explored: bool) {
+        if self.in_bounds(loc) {
+            self.explored[loc.y as usize][loc.x as usize] = explored;
+        }
+    }
+
+    /// Recalculates which tiles can be seen from a point, remembering every
+    /// tile that comes into view as explored.
+    pub fn compute_fov(&mut self, origin: Point, radius: i32) {
+        for row in self.visible.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = false;
+            }
+        }
+
+        let mut seen = vec![];
+        fov::compute(origin, radius,
+                     |loc| !self.in_bounds(loc) || !self.is_transparent(loc),
+                     |loc| seen.push(loc));
+
+        for loc in seen {
+            if self.in_bounds(loc) {
+                self.visible[loc.y as usize][loc.x as usize] = true;
+                self.explored[loc.y as usize][loc.x as usize] = true;
+            }
+        }
+    }
+
+    /// Returns the items lying on a tile, topmost last
+    pub fn items_at(&self, loc: Point) -> &[Item] {
+        if self.in_bounds(loc) {
+            &self.items[loc.y as usize][loc.x as usize]
+        } else {
+            &[]
+        }
+    }
+
+    /// Puts an item on top of anything already lying on a tile
+    pub fn place_item(&mut self, loc: Point, item: Item) {
+        self.items[loc.y as usize][loc.x as usize].push(item);
+    }
+
+    /// Removes an item from a tile, returning it if it was there
+    pub fn take_item(&mut self, loc: Point, index: usize) -> Option<Item> {
+        if !self.in_bounds(loc) {
+            return None;
+        }
+
+        let items = &mut self.items[loc.y as usize][loc.x as usize];
+        if index < items.len() {
+            Some(items.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the first point holding a tile, scanning row by row
+    pub fn find_tile(&self, tile: Tile) -> Option<Point> {
+        for (y, row) in self.tiles.iter().enumerate() {
+            if let Some(x) = row.iter().position(|&t| t == tile) {
+                return Some(Point::new(x as i32, y as i32));
+            }
+        }
+
+        None
+    }
+
     pub fn set_tile(&mut self, loc: Point, tile: Tile) {
         self.tiles[loc.y as usize][loc.x as usize] = tile;
     }
@@ -106,3 +241,134 @@ impl Map {
         self.tiles[0].len() as i32
     }
 }
+
+/// An error encountered while loading a map.
+///
+/// Line and column numbers start at one and refer to the original input,
+/// including any blank lines that were skipped.
+#[derive(Debug)]
+pub enum MapError {
+    /// The map could not be read
+    Io(io::Error),
+    /// The map contained no lines
+    Empty,
+    /// A line was a different width to the first line of the map
+    RaggedLine { line: usize, expected: usize, found: usize },
+    /// A character did not correspond to any tile
+    UnknownTile { line: usize, column: usize, c: char },
+    /// The map did not contain a starting position, `@`
+    NoStartingPosition { line: usize, column: usize },
+    /// The map contained more than one starting position, `@`
+    DuplicateStartingPosition { line: usize, column: usize },
+}
+
+impl MapError {
+    /// Returns the line and column at which the error occurred, if any
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            MapError::Io(_) => None,
+            MapError::Empty => Some((1, 1)),
+            MapError::RaggedLine { line, expected, found } => Some((line, cmp::min(expected, found) + 1)),
+            MapError::UnknownTile { line, column, .. } => Some((line, column)),
+            MapError::NoStartingPosition { line, column } => Some((line, column)),
+            MapError::DuplicateStartingPosition { line, column } => Some((line, column)),
+        }
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((line, column)) = self.position() {
+            write!(f, "line {}, column {}: ", line, column)?;
+        }
+
+        match *self {
+            MapError::Io(ref err) => write!(f, "could not read map: {}", err),
+            MapError::Empty => write!(f, "map contains no lines"),
+            MapError::RaggedLine { expected, found, .. } =>
+                write!(f, "line is {} characters wide, expected {}", found, expected),
+            MapError::UnknownTile { c, .. } => write!(f, "no valid tile for character {:?}", c),
+            MapError::NoStartingPosition { .. } => write!(f, "map does not contain a starting position, '@'"),
+            MapError::DuplicateStartingPosition { .. } => write!(f, "map contains more than one starting position, '@'"),
+        }
+    }
+}
+
+impl Error for MapError {}
+
+impl From<io::Error> for MapError {
+    fn from(err: io::Error) -> MapError {
+        MapError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Map, MapError};
+    use util::units::Point;
+
+    fn error(s: &str) -> MapError {
+        match Map::from_string(s.to_string()) {
+            Ok(_) => panic!("{:?} should not load", s),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn loads_a_map() {
+        let map = Map::from_string("#####\n#.@.#\n\n#####\n".to_string()).unwrap();
+
+        assert_eq!((map.width(), map.height()), (5, 3));
+        assert_eq!(map.starting_position, Point::new(2, 1));
+        assert!(map.is_walkable(Point::new(2, 1)));
+        assert!(!map.is_walkable(Point::new(0, 0)));
+    }
+
+    #[test]
+    fn rejects_ragged_lines() {
+        let e = error("#####\n#.@.#\n###");
+
+        match e {
+            MapError::RaggedLine { line: 3, expected: 5, found: 3 } => {}
+            other => panic!("expected a ragged line, got {:?}", other),
+        }
+        assert_eq!(e.position(), Some((3, 4)));
+    }
+
+    #[test]
+    fn reports_lines_counting_blank_ones() {
+        let e = error("#####\n\n#.@Z#\n#####");
+
+        match e {
+            MapError::UnknownTile { line: 3, column: 4, c: 'Z' } => {}
+            other => panic!("expected an unknown tile, got {:?}", other),
+        }
+        assert_eq!(e.to_string(), "line 3, column 4: no valid tile for character 'Z'");
+    }
+
+    #[test]
+    fn rejects_empty_maps() {
+        for s in ["", "\n\n", "\r\n"].iter() {
+            let e = error(s);
+
+            match e {
+                MapError::Empty => {}
+                other => panic!("expected an empty map, got {:?}", other),
+            }
+            assert_eq!(e.position(), Some((1, 1)));
+        }
+    }
+
+    #[test]
+    fn rejects_a_missing_or_second_start() {
+        match error("#####\n#...#\n#####") {
+            MapError::NoStartingPosition { line: 3, column: 6 } => {}
+            other => panic!("expected no starting position, got {:?}", other),
+        }
+
+        match error("#####\n#@..#\n#..@#\n#####") {
+            MapError::DuplicateStartingPosition { line: 3, column: 4 } => {}
+            other => panic!("expected a second starting position, got {:?}", other),
+        }
+    }
+}
@@ -1,16 +1,41 @@
 //! The roguelike's engine
 
+pub mod ai;
+pub mod combat;
+pub mod components;
+pub mod dice;
+pub mod ecs;
+pub mod fov;
+pub mod generators;
 pub mod log;
+pub mod path;
+pub mod replay;
+pub mod save;
+pub mod scheduler;
+pub mod status;
+pub mod systems;
+pub mod tiles;
 
-mod actor;
 mod game;
+mod item;
+mod level;
+mod loot;
 mod map;
-mod tiles;
+mod monsters;
+mod rng;
 mod world;
 
-pub use self::actor::Actor;
+pub use self::ai::Ai;
+pub use self::dice::{Dice, DiceError};
+pub use self::ecs::{Ecs, Entity, EntityBuilder};
 pub use self::game::{Game, Command};
-pub use self::log::{Message, MessageType};
-pub use self::map::Map;
-pub use self::tiles::Tile;
+pub use self::generators::MapGenerator;
+pub use self::item::{Item, ItemKind, Modifiers, Slot};
+pub use self::level::Level;
+pub use self::log::{Category, Message};
+pub use self::map::{Map, MapError};
+pub use self::replay::{Recorder, Replay, ReplayError};
+pub use self::rng::Rng;
+pub use self::save::SaveError;
+pub use self::tiles::{InvalidTileChar, Tile, TileDef, TileSet, TileSetError};
 pub use self::world::World;
@@ -0,0 +1,108 @@
+use engine::{Ai, Rng};
+use engine::components::{Energy, Equipment, Health, Inventory};
+use engine::ecs::EntityBuilder;
+use engine::scheduler::NORMAL_SPEED;
+use engine::status::{Status, StatusKind};
+use util::units::{Color, Point};
+
+/// A kind of monster which can be spawned into the world
+struct Monster {
+    name: &'static str,
+    glyph: char,
+    color: (u8, u8, u8),
+    max_health: i32,
+    attack: i32,
+    defence: i32,
+    ai: Ai,
+    /// The energy gained each tick, where `NORMAL_SPEED` is the player's
+    speed: i32,
+    /// A status inflicted on whatever the monster hits
+    on_hit: Option<Status>,
+    /// How often the monster appears, relative to the others
+    weight: u32,
+}
+
+static MONSTERS: [Monster; 5] = [
+    Monster {
+        name: "Rat",
+        glyph: 'r',
+        color: (150, 110, 80),
+        max_health: 6,
+        attack: 2,
+        defence: 0,
+        ai: Ai::Hunter { flee_percent: 50 },
+        speed: 12,
+        on_hit: None,
+        weight: 30,
+    },
+    Monster {
+        name: "Bat",
+        glyph: 'b',
+        color: (120, 100, 140),
+        max_health: 4,
+        attack: 1,
+        defence: 0,
+        ai: Ai::Wanderer,
+        speed: 20,
+        on_hit: None,
+        weight: 30,
+    },
+    Monster {
+        name: "Goblin",
+        glyph: 'g',
+        color: (90, 160, 60),
+        max_health: 12,
+        attack: 4,
+        defence: 1,
+        ai: Ai::Hunter { flee_percent: 25 },
+        speed: NORMAL_SPEED,
+        on_hit: None,
+        weight: 25,
+    },
+    Monster {
+        name: "Spider",
+        glyph: 's',
+        color: (170, 60, 170),
+        max_health: 8,
+        attack: 2,
+        defence: 0,
+        ai: Ai::Hunter { flee_percent: 20 },
+        speed: 12,
+        on_hit: Some(Status { kind: StatusKind::Poison, turns: 5, strength: 1 }),
+        weight: 12,
+    },
+    Monster {
+        name: "Orc",
+        glyph: 'o',
+        color: (60, 120, 40),
+        max_health: 20,
+        attack: 7,
+        defence: 2,
+        ai: Ai::Hunter { flee_percent: 10 },
+        speed: 5,
+        on_hit: None,
+        weight: 15,
+    },
+];
+
+/// Creates a randomly chosen monster at the given position
+pub fn random_monster(pos: Point, rng: &mut Rng) -> EntityBuilder {
+    let monster = rng.choose_weighted(&MONSTERS, |m| m.weight).expect("No monsters defined");
+    let (r, g, b) = monster.color;
+
+    let builder = EntityBuilder::new()
+        .with_name(monster.name)
+        .with_position(pos)
+        .with_health(Health::new(monster.max_health))
+        .with_glyph(monster.glyph, Color::new(r, g, b))
+        .with_stats(monster.attack, monster.defence)
+        .with_ai(monster.ai)
+        .with_energy(Energy::new(monster.speed))
+        .with_inventory(Inventory::new(0))
+        .with_equipment(Equipment::new());
+
+    match monster.on_hit {
+        Some(status) => builder.with_on_hit(status),
+        None => builder,
+    }
+}
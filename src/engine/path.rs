@@ -0,0 +1,409 @@
+//! Finding routes across the map
+//!
+//! `find_path` finds the cheapest route between two points with A*, for
+//! getting somewhere specific. A `DijkstraMap` instead records the cost of
+//! reaching a set of goals from everywhere on the map at once, so many
+//! actors can share it, and walking downhill on it approaches the goals.
+//! Its `fleeing` counterpart leads away from them without running into
+//! dead ends.
+//!
+//! Both only cross walkable tiles, and take a closure saying which other
+//! points are blocked, such as those with an actor standing on them.
+
+use engine::Map;
+use util::units::{Direction, Point};
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// The cost of each kind of step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Costs {
+    pub straight: u32,
+    /// The cost of a diagonal step, or `None` if they are not allowed
+    pub diagonal: Option<u32>,
+}
+
+impl Costs {
+    /// Only allows steps up, down, left and right
+    pub fn straight_only() -> Costs {
+        Costs { straight: 10, diagonal: None }
+    }
+
+    fn step(&self, direction: Direction) -> Option<u32> {
+        if direction.is_diagonal() { self.diagonal } else { Some(self.straight) }
+    }
+
+    /// The least it could cost to get between two points, if nothing is in
+    /// the way
+    fn estimate(&self, from: Point, to: Point) -> u32 {
+        let dx = (from.x - to.x).abs() as u32;
+        let dy = (from.y - to.y).abs() as u32;
+        let (long, short) = if dx > dy { (dx, dy) } else { (dy, dx) };
+
+        match self.diagonal {
+            Some(diagonal) if diagonal < self.straight * 2 => short * diagonal + (long - short) * self.straight,
+            _ => (long + short) * self.straight,
+        }
+    }
+}
+
+impl Default for Costs {
+    /// Diagonal steps cost roughly √2 times as much as straight ones, so
+    /// routes look natural while still using diagonals freely
+    fn default() -> Costs {
+        Costs { straight: 10, diagonal: Some(14) }
+    }
+}
+
+/// Finds the cheapest route from `from` to `to`.
+///
+/// The route does not include `from`, but ends with `to`. The destination
+/// may be blocked, so that actors can find their way to each other, but no
+/// other point on the route will be. Returns `None` if there is no route.
+pub fn find_path<F>(map: &Map, from: Point, to: Point, costs: Costs, is_blocked: F) -> Option<Vec<Point>>
+    where F: Fn(Point) -> bool {
+    if !map.in_bounds(from) || !map.in_bounds(to) || !map.is_walkable(to) {
+        return None;
+    }
+
+    if from == to {
+        return Some(vec![]);
+    }
+
+    let grid = Grid::new(map);
+    let mut cost = vec![u32::max_value(); grid.len()];
+    let mut came_from: Vec<Option<Point>> = vec![None; grid.len()];
+    let mut open = BinaryHeap::new();
+
+    cost[grid.index(from)] = 0;
+    open.push(Node { pos: from, priority: costs.estimate(from, to) });
+
+    while let Some(Node { pos, priority }) = open.pop() {
+        let current_cost = cost[grid.index(pos)];
+
+        if priority > current_cost + costs.estimate(pos, to) {
+            // A cheaper way here has already been found
+            continue;
+        }
+
+        if pos == to {
+            let mut path = vec![to];
+            let mut current = to;
+
+            while let Some(previous) = came_from[grid.index(current)] {
+                if previous == from {
+                    break;
+                }
+
+                path.push(previous);
+                current = previous;
+            }
+
+            path.reverse();
+            return Some(path);
+        }
+
+        for &direction in Direction::all().iter() {
+            let step = match costs.step(direction) {
+                Some(step) => step,
+                None => continue,
+            };
+
+            let next = pos.move_dir(direction);
+            if !map.in_bounds(next) || !map.is_walkable(next) || (next != to && is_blocked(next)) {
+                continue;
+            }
+
+            let next_cost = current_cost + step;
+            let index = grid.index(next);
+
+            if next_cost < cost[index] {
+                cost[index] = next_cost;
+                came_from[index] = Some(pos);
+                open.push(Node { pos: next, priority: next_cost + costs.estimate(next, to) });
+            }
+        }
+    }
+
+    None
+}
+
+/// How costly it is to reach the nearest of a set of goals from every point
+/// on a map
+#[derive(Clone, Debug)]
+pub struct DijkstraMap {
+    grid: Grid,
+    costs: Costs,
+    /// The cost from each point, or `None` where no goal can be reached
+    values: Vec<Option<i32>>,
+}
+
+impl DijkstraMap {
+    /// Builds a map leading to the nearest of `goals`
+    pub fn new<F>(map: &Map, goals: &[Point], costs: Costs, is_blocked: F) -> DijkstraMap
+        where F: Fn(Point) -> bool {
+        let sources: Vec<(Point, i32)> = goals.iter()
+                                              .filter(|&&goal| map.in_bounds(goal))
+                                              .map(|&goal| (goal, 0))
+                                              .collect();
+
+        DijkstraMap::relax(map, &sources, costs, is_blocked)
+    }
+
+    /// Builds a map for running away from this map's goals.
+    ///
+    /// Simply heading uphill gets an actor cornered, so instead every cost is
+    /// turned negative and scaled up before being spread out again. Points
+    /// far from the goals end up lowest, but so do points with space beyond
+    /// them, so heading downhill on the result leads past the goals into open
+    /// ground rather than into dead ends.
+    pub fn fleeing<F>(&self, map: &Map, costs: Costs, is_blocked: F) -> DijkstraMap
+        where F: Fn(Point) -> bool {
+        let sources: Vec<(Point, i32)> = self.grid.points()
+                                                  .filter_map(|pos| self.value(pos).map(|value| (pos, value * -6 / 5)))
+                                                  .collect();
+
+        DijkstraMap::relax(map, &sources, costs, is_blocked)
+    }
+
+    /// Returns the cost of reaching the nearest goal from a point, if any
+    /// goal can be reached
+    pub fn value(&self, pos: Point) -> Option<i32> {
+        if self.grid.contains(pos) { self.values[self.grid.index(pos)] } else { None }
+    }
+
+    /// Returns the direction of the lowest neighbouring point, if any is
+    /// lower than `pos` and not blocked. Following it repeatedly leads to
+    /// the nearest goal.
+    pub fn downhill<F>(&self, pos: Point, is_blocked: F) -> Option<Direction> where F: Fn(Point) -> bool {
+        let here = match self.value(pos) {
+            Some(value) => value,
+            None => return None,
+        };
+
+        Direction::all().iter()
+                        .filter(|&&d| self.costs.step(d).is_some() && !is_blocked(pos.move_dir(d)))
+                        .filter_map(|&d| self.value(pos.move_dir(d)).map(|value| (d, value)))
+                        .filter(|&(_, value)| value < here)
+                        .min_by_key(|&(_, value)| value)
+                        .map(|(d, _)| d)
+    }
+
+    /// Spreads costs out from some starting points across everything they
+    /// can reach
+    fn relax<F>(map: &Map, sources: &[(Point, i32)], costs: Costs, is_blocked: F) -> DijkstraMap
+        where F: Fn(Point) -> bool {
+        let grid = Grid::new(map);
+        let mut values: Vec<Option<i32>> = vec![None; grid.len()];
+        let mut open = BinaryHeap::new();
+
+        for &(pos, value) in sources.iter() {
+            let index = grid.index(pos);
+            if values[index].map_or(true, |v| value < v) {
+                values[index] = Some(value);
+                open.push(SignedNode { pos: pos, value: value });
+            }
+        }
+
+        while let Some(SignedNode { pos, value }) = open.pop() {
+            if values[grid.index(pos)] != Some(value) {
+                // A cheaper way here has already been found
+                continue;
+            }
+
+            for &direction in Direction::all().iter() {
+                let step = match costs.step(direction) {
+                    Some(step) => step as i32,
+                    None => continue,
+                };
+
+                let next = pos.move_dir(direction);
+                if !map.in_bounds(next) || !map.is_walkable(next) || is_blocked(next) {
+                    continue;
+                }
+
+                let index = grid.index(next);
+                let next_value = value + step;
+
+                if values[index].map_or(true, |v| next_value < v) {
+                    values[index] = Some(next_value);
+                    open.push(SignedNode { pos: next, value: next_value });
+                }
+            }
+        }
+
+        DijkstraMap { grid: grid, costs: costs, values: values }
+    }
+}
+
+/// The dimensions of a map, for storing a value per point in a `Vec`
+#[derive(Clone, Copy, Debug)]
+struct Grid {
+    width: i32,
+    height: i32,
+}
+
+impl Grid {
+    fn new(map: &Map) -> Grid {
+        Grid { width: map.width(), height: map.height() }
+    }
+
+    fn len(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    fn contains(&self, pos: Point) -> bool {
+        pos.x >= 0 && pos.y >= 0 && pos.x < self.width && pos.y < self.height
+    }
+
+    fn index(&self, pos: Point) -> usize {
+        (pos.y * self.width + pos.x) as usize
+    }
+
+    fn points(&self) -> Box<Iterator<Item = Point>> {
+        let (width, height) = (self.width, self.height);
+        Box::new((0..height).flat_map(move |y| (0..width).map(move |x| Point::new(x, y))))
+    }
+}
+
+/// A point waiting to be searched by A*, ordered so that `BinaryHeap` pops
+/// the lowest priority first
+#[derive(Clone, Copy, PartialEq)]
+struct Node {
+    pos: Point,
+    priority: u32,
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A point waiting to be spread from when building a `DijkstraMap`, which
+/// may have a negative value
+#[derive(Clone, Copy, PartialEq)]
+struct SignedNode {
+    pos: Point,
+    value: i32,
+}
+
+impl Eq for SignedNode {}
+
+impl Ord for SignedNode {
+    fn cmp(&self, other: &SignedNode) -> Ordering {
+        other.value.cmp(&self.value)
+    }
+}
+
+impl PartialOrd for SignedNode {
+    fn partial_cmp(&self, other: &SignedNode) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_path, Costs, DijkstraMap};
+    use engine::Map;
+    use util::units::Point;
+
+    /// A corridor bending around a wall, with a room cut off from the rest
+    fn map() -> Map {
+        Map::from_string("##########\n\
+                          #@.......#\n\
+                          ########.#\n\
+                          #........#\n\
+                          #.########\n\
+                          #..#.....#\n\
+                          ##########".to_string()).unwrap()
+    }
+
+    /// Whether every step of a path is onto a neighbouring walkable point
+    fn is_connected(map: &Map, from: Point, path: &[Point]) -> bool {
+        let mut previous = from;
+
+        path.iter().all(|&pos| {
+            let adjacent = (pos.x - previous.x).abs() <= 1 && (pos.y - previous.y).abs() <= 1;
+            previous = pos;
+            adjacent && map.is_walkable(pos)
+        })
+    }
+
+    #[test]
+    fn finds_the_shortest_way_around_walls() {
+        let map = map();
+        let from = Point::new(1, 1);
+        let to = Point::new(1, 5);
+
+        let path = find_path(&map, from, to, Costs::straight_only(), |_| false).unwrap();
+
+        assert_eq!(path.last(), Some(&to));
+        assert!(is_connected(&map, from, &path));
+        // Along the top, down the right, back along the middle and down
+        assert_eq!(path.len(), 7 + 2 + 7 + 2);
+
+        let diagonal = find_path(&map, from, to, Costs::default(), |_| false).unwrap();
+        assert!(is_connected(&map, from, &diagonal));
+        assert!(diagonal.len() < path.len());
+    }
+
+    #[test]
+    fn goes_around_blocked_points_but_not_through_walls() {
+        let map = map();
+        let from = Point::new(1, 1);
+
+        let blocked = |pos: Point| pos == Point::new(8, 2);
+        assert_eq!(find_path(&map, from, Point::new(1, 5), Costs::default(), blocked), None);
+
+        // The room on the bottom right cannot be reached at all
+        assert_eq!(find_path(&map, from, Point::new(6, 5), Costs::default(), |_| false), None);
+        assert_eq!(find_path(&map, from, Point::new(0, 0), Costs::default(), |_| false), None);
+    }
+
+    #[test]
+    fn downhill_leads_to_the_goal() {
+        let map = map();
+        let goal = Point::new(1, 5);
+        let towards = DijkstraMap::new(&map, &[goal], Costs::default(), |_| false);
+
+        assert_eq!(towards.value(goal), Some(0));
+        assert_eq!(towards.value(Point::new(6, 5)), None);
+
+        let mut pos = Point::new(1, 1);
+        for _ in 0..20 {
+            match towards.downhill(pos, |_| false) {
+                Some(direction) => pos = pos.move_dir(direction),
+                None => break,
+            }
+        }
+
+        assert_eq!(pos, goal);
+    }
+
+    #[test]
+    fn fleeing_moves_away_from_the_goal() {
+        let map = map();
+        let player = Point::new(4, 3);
+        let towards = DijkstraMap::new(&map, &[player], Costs::default(), |_| false);
+        let away = towards.fleeing(&map, Costs::default(), |_| false);
+
+        let mut pos = Point::new(5, 3);
+        for _ in 0..3 {
+            let direction = away.downhill(pos, |_| false).expect("There is nowhere to flee to");
+            let next = pos.move_dir(direction);
+
+            assert!(towards.value(next) > towards.value(pos));
+            pos = next;
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Recording games and playing them back
+//!
+//! Everything random in a game comes from its seed, so a game can be
+//! reproduced exactly from the seed and the commands the player gave. A
+//! replay file holds just that: a header, the seed, and one line per
+//! command with the turn it was given on. Commands are written as they
+//! happen, so the file is complete even if the game crashes.
+
+use engine::{Command, Game, Slot};
+use util::units::Direction;
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// The current version of the replay format.
+///
+/// Replays only hold commands, so this is bumped whenever the rules change
+/// how a game plays out as well as when the format changes. Version 2 came
+/// with speed-based turns and status effects.
+pub const VERSION: u32 = 2;
+
+/// Where games are recorded to unless told otherwise
+pub static DEFAULT_PATH: &'static str = "replay.txt";
+
+/// The first word of every replay file
+static MAGIC: &'static str = "verbonia-replay";
+
+/// An error encountered while recording or replaying a game
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The replay could not be read or written
+    Io(io::Error),
+    /// The file is not a replay at all
+    NotAReplay,
+    /// The replay was written by a newer version of the game
+    TooNew { version: u32 },
+    /// The replay was written under older rules, so it would not play out
+    /// the same way
+    TooOld { version: u32 },
+    /// The replay is damaged or was edited by hand
+    Corrupt { line: usize, reason: String },
+    /// The game has no known seed, so it cannot be reproduced
+    UnknownSeed,
+    /// The game being replayed has gone differently from the one recorded
+    Desync { line: usize, expected_turn: u32, actual_turn: u32 },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io(ref err) => write!(f, "could not access replay: {}", err),
+            ReplayError::NotAReplay => write!(f, "file is not a replay"),
+            ReplayError::TooNew { version } =>
+                write!(f, "replay is from a newer version of the game (format {}, expected at most {})", version, VERSION),
+            ReplayError::TooOld { version } =>
+                write!(f, "replay is from an older version of the game and cannot be played back (format {}, expected {})",
+                       version, VERSION),
+            ReplayError::Corrupt { line, ref reason } => write!(f, "replay is corrupt at line {}: {}", line, reason),
+            ReplayError::UnknownSeed => write!(f, "the game's seed is unknown, so it cannot be recorded"),
+            ReplayError::Desync { line, expected_turn, actual_turn } =>
+                write!(f, "replay went out of sync at line {}: recorded on turn {}, but the game is on turn {}",
+                       line, expected_turn, actual_turn),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> ReplayError {
+        ReplayError::Io(err)
+    }
+}
+
+/// Writes each command given to a game to a replay file
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Starts a new replay file for a game, replacing any existing file.
+    ///
+    /// The game must not have had any commands yet, or the replay would not
+    /// reproduce it.
+    pub fn create<P>(path: P, game: &Game) -> Result<Recorder, ReplayError> where P: AsRef<Path> {
+        let seed = game.seed.ok_or(ReplayError::UnknownSeed)?;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "{} {}", MAGIC, VERSION)?;
+        writeln!(out, "seed {}", seed)?;
+        out.flush()?;
+
+        Ok(Recorder { out: out })
+    }
+
+    /// Records a command given on a turn
+    pub fn record(&mut self, turn: u32, command: Command) -> io::Result<()> {
+        writeln!(self.out, "command {} {}", turn, format_command(command))?;
+        self.out.flush()
+    }
+}
+
+/// A recorded game, which can be played back one command at a time
+pub struct Replay {
+    seed: u64,
+    /// Each command with the turn it was given on and its line in the file
+    commands: Vec<(u32, Command, usize)>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn load<P>(path: P) -> Result<Replay, ReplayError> where P: AsRef<Path> {
+        let file = File::open(path)?;
+        Replay::read(BufReader::new(file))
+    }
+
+    /// Reads a replay in the replay format
+    pub fn read<R>(input: R) -> Result<Replay, ReplayError> where R: BufRead {
+        let mut lines = input.lines();
+
+        let header = match lines.next() {
+            Some(line) => line?,
+            None => return Err(ReplayError::NotAReplay),
+        };
+
+        let mut header_fields = header.split(' ');
+        if header_fields.next() != Some(MAGIC) {
+            return Err(ReplayError::NotAReplay);
+        }
+
+        let version: u32 = match header_fields.next().and_then(|v| v.parse().ok()) {
+            Some(version) => version,
+            None => return Err(ReplayError::NotAReplay),
+        };
+
+        if version > VERSION {
+            return Err(ReplayError::TooNew { version: version });
+        } else if version < VERSION {
+            return Err(ReplayError::TooOld { version: version });
+        }
+
+        let corrupt = |line: usize, reason: &str| ReplayError::Corrupt { line: line, reason: reason.to_string() };
+
+        let seed = match lines.next() {
+            Some(line) => {
+                let line = line?;
+                match line.split(' ').collect::<Vec<_>>().as_slice() {
+                    &["seed", seed] => seed.parse().map_err(|_| corrupt(2, "invalid seed"))?,
+                    _ => return Err(corrupt(2, "expected \"seed\"")),
+                }
+            }
+            None => return Err(corrupt(2, "expected \"seed\", found the end of the file")),
+        };
+
+        let mut commands = vec![];
+
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 3;
+            let line = line?;
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.splitn(3, ' ').collect();
+            if fields.len() != 3 || fields[0] != "command" {
+                return Err(corrupt(line_number, "expected \"command <turn> <command>\""));
+            }
+
+            let turn = fields[1].parse().map_err(|_| corrupt(line_number, "invalid turn"))?;
+            let command = parse_command(fields[2])
+                              .ok_or_else(|| corrupt(line_number, &format!("unknown command {:?}", fields[2])))?;
+
+            commands.push((turn, command, line_number));
+        }
+
+        Ok(Replay { seed: seed, commands: commands, next: 0 })
+    }
+
+    /// Creates the game as it was before the first command
+    pub fn new_game(&self) -> Game {
+        Game::with_seed(self.seed)
+    }
+
+    pub fn seed(&self) -> u64 { self.seed }
+
+    /// The number of commands played back so far
+    pub fn position(&self) -> usize { self.next }
+
+    /// The number of commands in the replay
+    pub fn len(&self) -> usize { self.commands.len() }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.commands.len()
+    }
+
+    /// Gives the next recorded command to a game.
+    ///
+    /// Returns false once every command has been played back.
+    pub fn step(&mut self, game: &mut Game) -> Result<bool, ReplayError> {
+        let (turn, command, line) = match self.commands.get(self.next) {
+            Some(&entry) => entry,
+            None => return Ok(false),
+        };
+
+        if turn != game.turn {
+            return Err(ReplayError::Desync { line: line, expected_turn: turn, actual_turn: game.turn });
+        }
+
+        game.do_command(command);
+        self.next += 1;
+        Ok(true)
+    }
+
+    /// Plays back every remaining command
+    pub fn run(&mut self, game: &mut Game) -> Result<(), ReplayError> {
+        while self.step(game)? {}
+        Ok(())
+    }
+}
+
+fn format_command(command: Command) -> String {
+    match command {
+        Command::Walk(direction) => format!("walk {}", direction_name(direction)),
+        Command::PickUp => "pickup".to_string(),
+        Command::Drop(index) => format!("drop {}", index),
+        Command::Use(index) => format!("use {}", index),
+        Command::Unequip(slot) => format!("unequip {}", slot.name().to_lowercase()),
+        Command::Descend => "descend".to_string(),
+        Command::Ascend => "ascend".to_string(),
+    }
+}
+
+fn parse_command(text: &str) -> Option<Command> {
+    let fields: Vec<&str> = text.split(' ').collect();
+
+    match fields.as_slice() {
+        &["walk", direction] => DIRECTIONS.iter()
+                                          .find(|&&d| direction_name(d) == direction)
+                                          .map(|&d| Command::Walk(d)),
+        &["pickup"] => Some(Command::PickUp),
+        &["drop", index] => index.parse().ok().map(Command::Drop),
+        &["use", index] => index.parse().ok().map(Command::Use),
+        &["unequip", slot] => Slot::all().iter()
+                                         .find(|s| s.name().to_lowercase() == slot)
+                                         .map(|&s| Command::Unequip(s)),
+        &["descend"] => Some(Command::Descend),
+        &["ascend"] => Some(Command::Ascend),
+        _ => None,
+    }
+}
+
+static DIRECTIONS: [Direction; 9] = [
+    Direction::Up, Direction::Down, Direction::Left, Direction::Right,
+    Direction::UpLeft, Direction::UpRight, Direction::DownLeft, Direction::DownRight,
+    Direction::Wait,
+];
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+        Direction::UpLeft => "up-left",
+        Direction::UpRight => "up-right",
+        Direction::DownLeft => "down-left",
+        Direction::DownRight => "down-right",
+        Direction::Wait => "wait",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_command, parse_command, Replay, ReplayError, DIRECTIONS, VERSION};
+    use engine::{Command, Slot};
+
+    fn every_command() -> Vec<Command> {
+        let mut commands: Vec<Command> = DIRECTIONS.iter().map(|&d| Command::Walk(d)).collect();
+        commands.extend(Slot::all().iter().map(|&s| Command::Unequip(s)));
+        commands.extend(vec![
+            Command::PickUp,
+            Command::Drop(0),
+            Command::Drop(25),
+            Command::Use(3),
+            Command::Descend,
+            Command::Ascend,
+        ]);
+        commands
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        for command in every_command() {
+            let text = format_command(command);
+            assert_eq!(parse_command(&text), Some(command), "{:?} was written as {:?}", command, text);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        for text in ["", "fly", "walk", "walk sideways", "drop x", "unequip hat", "pickup now"].iter() {
+            assert_eq!(parse_command(text), None, "{:?} should not parse", text);
+        }
+    }
+
+    #[test]
+    fn reads_commands() {
+        let text = format!("verbonia-replay {}\nseed 12\ncommand 0 walk up\ncommand 1 pickup\n", VERSION);
+        let replay = Replay::read(text.as_bytes()).unwrap();
+
+        assert_eq!(replay.seed(), 12);
+        assert_eq!(replay.len(), 2);
+    }
+
+    #[test]
+    fn rejects_older_replays() {
+        let text = format!("verbonia-replay {}\nseed 12\ncommand 0 walk up\n", VERSION - 1);
+
+        match Replay::read(text.as_bytes()) {
+            Err(ReplayError::TooOld { version }) => assert_eq!(version, VERSION - 1),
+            other => panic!("expected the replay to be too old, got {:?}", other.map(|_| ())),
+        }
+    }
+}
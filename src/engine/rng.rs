@@ -0,0 +1,179 @@
+use engine::Dice;
+use engine::dice;
+
+/// A small, fast pseudo-random number generator (xorshift64*).
+///
+/// The same seed always produces the same sequence of numbers on every
+/// platform, so anything built from it can be reproduced exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a new generator from a seed
+    pub fn new(seed: u64) -> Rng {
+        // Scramble the seed (splitmix64) so that similar seeds give unrelated
+        // sequences, and so that the state is never zero.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Rng { state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z } }
+    }
+
+    /// Restores a generator from a state previously returned by `state`
+    pub fn from_state(state: u64) -> Rng {
+        Rng { state: if state == 0 { 0x9E37_79B9_7F4A_7C15 } else { state } }
+    }
+
+    /// Returns the generator's internal state, so that it can be saved
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Returns a number in the half-open range `[low, high)`
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        assert!(low < high, "Empty range {}..{}", low, high);
+        let span = (high as i64 - low as i64) as u64;
+        (low as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// Returns true with a probability of `percent` in 100
+    pub fn chance(&mut self, percent: u32) -> bool {
+        (self.next_u64() % 100) < percent as u64
+    }
+
+    /// Returns a random element of a slice, or `None` if it is empty
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            let index = (self.next_u64() % items.len() as u64) as usize;
+            Some(&items[index])
+        }
+    }
+
+    /// Rolls some dice, returning their total. Dice with no sides roll
+    /// nothing, and totals too large for an `i32` stop at its limits.
+    pub fn roll(&mut self, dice: Dice) -> i32 {
+        if dice.sides == 0 {
+            return dice.modifier;
+        }
+
+        let sides = dice.sides as u64;
+        let rolled = (0..dice.count).fold(0i64, |total, _| total.saturating_add((self.next_u64() % sides + 1) as i64));
+        dice::saturate(rolled.saturating_add(dice.modifier as i64))
+    }
+
+    /// Returns a random element of a slice, where elements with a higher
+    /// `weight` are chosen more often. Elements with a weight of zero are
+    /// never chosen, and `None` is returned if every weight is zero.
+    pub fn choose_weighted<'a, T, F>(&mut self, items: &'a [T], weight: F) -> Option<&'a T>
+        where F: Fn(&T) -> u32 {
+        let total: u64 = items.iter().map(|item| weight(item) as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = self.next_u64() % total;
+
+        for item in items {
+            let weight = weight(item) as u64;
+            if target < weight {
+                return Some(item);
+            }
+            target -= weight;
+        }
+
+        unreachable!("The target is always below the total weight")
+    }
+
+    /// Puts the elements of a slice into a random order
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+    use engine::Dice;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_sequences() {
+        let first: Vec<u64> = { let mut rng = Rng::new(1); (0..10).map(|_| rng.next_u64()).collect() };
+        let second: Vec<u64> = { let mut rng = Rng::new(2); (0..10).map(|_| rng.next_u64()).collect() };
+
+        assert!(first != second);
+    }
+
+    #[test]
+    fn restoring_state_continues_the_sequence() {
+        let mut rng = Rng::new(7);
+        rng.next_u64();
+
+        let mut restored = Rng::from_state(rng.state());
+        assert_eq!(rng.next_u64(), restored.next_u64());
+    }
+
+    #[test]
+    fn rolls_stay_in_range() {
+        let mut rng = Rng::new(3);
+
+        for &dice in [Dice::new(1, 6, 0), Dice::new(3, 6, 2), Dice::new(2, 4, -1), Dice::new(100, 1000, 0)].iter() {
+            let rolls: Vec<i32> = (0..500).map(|_| rng.roll(dice)).collect();
+
+            assert!(rolls.iter().all(|&r| r >= dice.min() && r <= dice.max()), "{} rolled out of range", dice);
+        }
+
+        let rolls: Vec<i32> = (0..500).map(|_| rng.roll(Dice::new(1, 6, 0))).collect();
+        assert!(rolls.contains(&1) && rolls.contains(&6));
+    }
+
+    #[test]
+    fn huge_rolls_saturate() {
+        let mut rng = Rng::new(3);
+        let dice = Dice::new(2, u32::max_value(), i32::max_value());
+
+        assert_eq!(rng.roll(dice), i32::max_value());
+        assert_eq!(rng.roll(Dice::new(5, 0, 3)), 3);
+    }
+
+    #[test]
+    fn shuffle_keeps_every_element() {
+        let mut rng = Rng::new(9);
+        let mut items: Vec<u32> = (0..50).collect();
+
+        rng.shuffle(&mut items);
+        assert!(items != (0..50).collect::<Vec<_>>());
+
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,833 @@
+//! Saving and loading games
+//!
+//! Saves are plain text with one record per line. Each line begins with a
+//! tag naming the record, followed by its fields separated by spaces. Free
+//! text such as names and messages always comes last on its line, with
+//! backslashes and newlines escaped.
+//!
+//! The first line records the format version. Whenever the format changes,
+//! `VERSION` is bumped and `read` learns how to fill in anything missing
+//! from saves written by older versions.
+//!
+//! Version history:
+//!
+//! 1. The first version.
+//! 2. Adds items on the map, actors' inventories and their capacity.
+//! 3. Adds equipment. Actors' stats are stored without it.
+//! 4. Adds the player's depth and the other levels of the dungeon.
+//! 5. Adds the seed the game was created from, or `unknown` if it was
+//!    loaded from an older save.
+//! 6. Adds the game time and each actor's speed and energy.
+//! 7. Adds status effects, elixirs, and statuses inflicted by monsters'
+//!    attacks.
+//! 8. Messages record their turn, category and repeat count, and may contain
+//!    colour spans. Older `info` messages become system messages, and older
+//!    `error` messages become red system messages.
+
+use engine::{Ai, Game, Item, ItemKind, Level, Map, Modifiers, Rng, Slot, Tile, World};
+use engine::components::{Energy, Equipment, Health, Inventory};
+use engine::ecs::{Ecs, Entity, EntityBuilder};
+use engine::log::{self, Category, Message, MessageList};
+use engine::scheduler::{NORMAL_SPEED, TICKS_PER_TURN};
+use engine::status::{Status, StatusKind, Statuses};
+use engine::world::PLAYER_CAPACITY;
+use util::FromChar;
+use util::units::{Color, Point};
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The current version of the save format
+pub const VERSION: u32 = 8;
+
+/// Where the game is saved to unless told otherwise
+pub static DEFAULT_PATH: &'static str = "savegame.txt";
+
+/// The first word of every save file
+static MAGIC: &'static str = "verbonia-save";
+
+/// The widest or tallest map a save may contain, so that a corrupt size
+/// cannot make loading try to allocate an enormous map
+const MAX_MAP_SIZE: usize = 1000;
+
+/// An error encountered while saving or loading a game
+#[derive(Debug)]
+pub enum SaveError {
+    /// The save could not be read or written
+    Io(io::Error),
+    /// The file is not a save file at all
+    NotASave,
+    /// The save was written by a newer version of the game
+    TooNew { version: u32 },
+    /// The save was written by a version of the game which is no longer supported
+    TooOld { version: u32 },
+    /// The save is damaged or was edited by hand
+    Corrupt { line: usize, reason: String },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref err) => write!(f, "could not access save: {}", err),
+            SaveError::NotASave => write!(f, "file is not a saved game"),
+            SaveError::TooNew { version } =>
+                write!(f, "save is from a newer version of the game (format {}, expected at most {})", version, VERSION),
+            SaveError::TooOld { version } =>
+                write!(f, "save is from an old version of the game which is no longer supported (format {})", version),
+            SaveError::Corrupt { line, ref reason } => write!(f, "save is corrupt at line {}: {}", line, reason),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> SaveError {
+        SaveError::Io(err)
+    }
+}
+
+/// Saves a game to a file, replacing any existing save there.
+///
+/// The save is written to a temporary file first so that a failure part way
+/// through never destroys the previous save.
+pub fn save_to_file<P>(game: &Game, path: P) -> Result<(), SaveError> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let temp_path = path.with_extension("tmp");
+
+    {
+        let mut out = BufWriter::new(File::create(&temp_path)?);
+        write(game, &mut out)?;
+        out.flush()?;
+    }
+
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Loads a game and its messages from a file
+pub fn load_from_file<P>(path: P) -> Result<(Game, MessageList), SaveError> where P: AsRef<Path> {
+    let file = File::open(path)?;
+    read(BufReader::new(file))
+}
+
+/// Writes a game in the save format
+pub fn write<W>(game: &Game, out: &mut W) -> io::Result<()> where W: Write {
+    let world = &game.world;
+
+    writeln!(out, "{} {}", MAGIC, VERSION)?;
+    writeln!(out, "turn {}", game.turn)?;
+    writeln!(out, "time {}", game.time)?;
+    writeln!(out, "rng {}", game.rng.state())?;
+
+    match game.seed {
+        Some(seed) => writeln!(out, "seed {}", seed)?,
+        None => writeln!(out, "seed unknown")?,
+    }
+
+    writeln!(out, "depth {}", world.depth)?;
+    write_map(out, &world.map)?;
+    let player = world.player();
+    write_actor(out, "player", &world.entities, player)?;
+
+    for entity in world.entities.entities().into_iter().filter(|&e| e != player) {
+        write_actor(out, "actor", &world.entities, entity)?;
+    }
+
+    for (i, level) in world.levels.iter().enumerate() {
+        if let Some(ref level) = *level {
+            writeln!(out, "level {}", i + 1)?;
+            write_map(out, &level.map)?;
+
+            for entity in level.entities.entities() {
+                write_actor(out, "actor", &level.entities, entity)?;
+            }
+        }
+    }
+
+    let messages: Vec<Message> = log::LOG.with(|l| l.borrow().items().cloned().collect());
+
+    // The log lists the newest message first, but saves store them in the
+    // order they were written
+    for message in messages.iter().rev() {
+        writeln!(out, "message {} {} {} {}",
+                 message.turn(), message.category().name(), message.count(), escape(message.markup()))?;
+    }
+
+    writeln!(out, "end")
+}
+
+fn write_map<W>(out: &mut W, map: &Map) -> io::Result<()> where W: Write {
+    writeln!(out, "map {} {} {} {}", map.width(), map.height(), map.starting_position.x, map.starting_position.y)?;
+
+    for row in map.tiles.iter() {
+        let line: String = row.iter().map(|t| t.to_char()).collect();
+        writeln!(out, "tiles {}", line)?;
+    }
+
+    for y in 0..map.height() {
+        let line: String = (0..map.width()).map(|x| if map.is_explored(Point::new(x, y)) { '1' } else { '0' })
+                                            .collect();
+        writeln!(out, "explored {}", line)?;
+    }
+
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            for item in map.items_at(Point::new(x, y)) {
+                writeln!(out, "item {} {} {}", x, y, format_item(item))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes an entity as an actor. Any component it is missing is written
+/// with a default, so that every actor line has the same fields.
+fn write_actor<W>(out: &mut W, tag: &str, entities: &Ecs, entity: Entity) -> io::Result<()> where W: Write {
+    let ai = match entities.ais.get(entity) {
+        None => "none".to_string(),
+        Some(&Ai::Wanderer) => "wanderer".to_string(),
+        Some(&Ai::Hunter { flee_percent }) => format!("hunter:{}", flee_percent),
+    };
+
+    let pos = entities.position(entity).unwrap_or(Point::zero());
+    let health = entities.healths.get(entity).cloned().unwrap_or(Health::new(0));
+    let (attack, defence) = entities.stats.get(entity).map_or((0, 0), |s| (s.attack, s.defence));
+    let capacity = entities.inventories.get(entity).map_or(0, |i| i.capacity);
+    let energy = entities.energies.get(entity).cloned().unwrap_or(Energy::new(0));
+    let (glyph, color) = entities.renderables.get(entity).map_or(('?', Color::new(255, 255, 255)), |r| (r.glyph, r.color));
+
+    writeln!(out, "{} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+             tag, pos.x, pos.y,
+             health.current, health.base_max, attack, defence, capacity, energy.speed, energy.energy,
+             glyph as u32, color.r, color.g, color.b,
+             ai, escape(entities.name(entity)))?;
+
+    for item in Slot::all().iter().filter_map(|&slot| entities.equipped(entity, slot)) {
+        writeln!(out, "wear {}", format_item(item))?;
+    }
+
+    for item in entities.items(entity) {
+        writeln!(out, "carry {}", format_item(item))?;
+    }
+
+    for status in entities.statuses.get(entity).iter().flat_map(|s| s.iter()) {
+        writeln!(out, "status {}", format_status(status))?;
+    }
+
+    if let Some(on_hit) = entities.on_hits.get(entity) {
+        writeln!(out, "on_hit {}", format_status(&on_hit.0))?;
+    }
+
+    Ok(())
+}
+
+fn format_item(item: &Item) -> String {
+    let kind = match item.kind() {
+        ItemKind::Potion { heal } => format!("potion:{}", heal),
+        ItemKind::Elixir { status } => format!("elixir:{}", format_status(&status).replace(' ', ":")),
+        ItemKind::Treasure => "treasure".to_string(),
+        ItemKind::Equipment { slot, modifiers } => format!("equipment:{}:{}:{}:{}", slot_name(slot),
+                                                           modifiers.attack, modifiers.defence, modifiers.max_health),
+    };
+
+    let color = item.color();
+    format!("{} {} {} {} {} {}", kind, item.glyph() as u32, color.r, color.g, color.b, escape(item.name()))
+}
+
+fn format_status(status: &Status) -> String {
+    format!("{} {} {}", status.kind.id(), status.turns, status.strength)
+}
+
+/// Reads a game and its messages in the save format, upgrading it from
+/// older versions where possible.
+///
+/// The messages are handed back rather than put in the log, so that nothing
+/// changes until the whole save has been read.
+pub fn read<R>(input: R) -> Result<(Game, MessageList), SaveError> where R: BufRead {
+    let mut reader = Reader::new(input);
+
+    let header = match reader.next()? {
+        Some(line) => line,
+        None => return Err(SaveError::NotASave),
+    };
+
+    let mut header_fields = header.split(' ');
+    if header_fields.next() != Some(MAGIC) {
+        return Err(SaveError::NotASave);
+    }
+
+    let version: u32 = match header_fields.next().and_then(|v| v.parse().ok()) {
+        Some(version) => version,
+        None => return Err(SaveError::NotASave),
+    };
+
+    if version > VERSION {
+        return Err(SaveError::TooNew { version: version });
+    } else if version < 1 {
+        return Err(SaveError::TooOld { version: version });
+    }
+
+    let turn_line = reader.expect("turn")?;
+    let turn: u32 = reader.parse(&turn_line)?;
+
+    let time = if version < 6 {
+        turn as u64 * TICKS_PER_TURN
+    } else {
+        let time_line = reader.expect("time")?;
+        reader.parse(&time_line)?
+    };
+
+    let rng_line = reader.expect("rng")?;
+    let rng = Rng::from_state(reader.parse(&rng_line)?);
+
+    let seed = if version < 5 {
+        None
+    } else {
+        match reader.expect("seed")?.as_str() {
+            "unknown" => None,
+            seed => Some(reader.parse(seed)?),
+        }
+    };
+
+    let depth = if version < 4 {
+        1
+    } else {
+        let depth_line = reader.expect("depth")?;
+        reader.parse(&depth_line)?
+    };
+
+    if depth < 1 {
+        return Err(reader.corrupt("depth must be at least 1"));
+    }
+
+    let map = read_map(&mut reader)?;
+
+    let player_line = reader.expect("player")?;
+    let player = read_actor(&mut reader, &player_line, version, PLAYER_CAPACITY, Energy::ready(NORMAL_SPEED))?;
+
+    let mut entities = Ecs::new();
+    entities.spawn(player.controlled());
+    read_actors(&mut reader, version, &mut entities)?;
+
+    let mut world = World {
+        entities: entities,
+        map: map,
+        depth: depth,
+        levels: Vec::new(),
+    };
+
+    while let Some(level_line) = reader.optional("level")? {
+        let level_depth: u32 = reader.parse(&level_line)?;
+        let index = level_depth.wrapping_sub(1) as usize;
+
+        if level_depth < 1 || level_depth == depth || world.levels.get(index).map_or(false, |l| l.is_some()) {
+            return Err(reader.corrupt(&format!("unexpected level {}", level_depth)));
+        }
+
+        let map = read_map(&mut reader)?;
+        let mut entities = Ecs::new();
+        read_actors(&mut reader, version, &mut entities)?;
+
+        while world.levels.len() <= index {
+            world.levels.push(None);
+        }
+        world.levels[index] = Some(Level { map: map, entities: entities });
+    }
+
+    let mut messages = MessageList::new();
+    messages.set_turn(turn);
+
+    while let Some(message_line) = reader.optional("message")? {
+        let message = if version < 8 {
+            let fields = reader.fields(&message_line, 2)?;
+            let text = unescape(fields[1]);
+
+            match fields[0] {
+                "info" => Message::new(Category::System, 0, &log::escape(&text)),
+                "error" => Message::new(Category::System, 0, &format!("{{red:{}}}", log::escape(&text))),
+                other => return Err(reader.corrupt(&format!("unknown message type {:?}", other))),
+            }
+        } else {
+            let fields = reader.fields(&message_line, 4)?;
+            let category = Category::named(fields[1])
+                .ok_or_else(|| reader.corrupt(&format!("unknown message category {:?}", fields[1])))?;
+
+            Message::new(category, reader.parse(fields[0])?, &unescape(fields[3])).with_count(reader.parse(fields[2])?)
+        };
+
+        messages.push(message);
+    }
+
+    reader.expect("end")?;
+
+    if !world.map.in_bounds(world.player_pos()) {
+        return Err(reader.corrupt("player is outside the map"));
+    }
+
+    world.update_fov();
+
+    Ok((Game::from_parts(world, turn, time, seed, rng), messages))
+}
+
+fn read_map<R>(reader: &mut Reader<R>) -> Result<Map, SaveError> where R: BufRead {
+    let map_line = reader.expect("map")?;
+    let map_fields = reader.fields(&map_line, 4)?;
+    let width: usize = reader.parse(map_fields[0])?;
+    let height: usize = reader.parse(map_fields[1])?;
+    let starting_position = Point::new(reader.parse(map_fields[2])?, reader.parse(map_fields[3])?);
+
+    if width == 0 || height == 0 || width > MAX_MAP_SIZE || height > MAX_MAP_SIZE {
+        return Err(reader.corrupt(&format!("invalid map size {}x{}", width, height)));
+    }
+
+    if starting_position.x < 0 || starting_position.y < 0
+        || starting_position.x as usize >= width || starting_position.y as usize >= height {
+        return Err(reader.corrupt("starting position is outside the map"));
+    }
+
+    let mut tiles = Vec::with_capacity(height);
+    for _ in 0..height {
+        let row = reader.expect("tiles")?;
+        let row: Vec<Tile> = row.chars().map(Tile::from_char).collect::<Result<_, _>>()
+                                .map_err(|e| reader.corrupt(&e.to_string()))?;
+
+        if row.len() != width {
+            return Err(reader.corrupt(&format!("expected {} tiles, found {}", width, row.len())));
+        }
+
+        tiles.push(row);
+    }
+
+    let mut map = Map::from_tiles(tiles, starting_position);
+
+    for y in 0..height {
+        let row = reader.expect("explored")?;
+
+        if row.len() != width {
+            return Err(reader.corrupt(&format!("expected {} flags, found {}", width, row.len())));
+        }
+
+        for (x, flag) in row.chars().enumerate() {
+            let explored = match flag {
+                '0' => false,
+                '1' => true,
+                _ => return Err(reader.corrupt(&format!("invalid explored flag {:?}", flag))),
+            };
+            map.set_explored(Point::new(x as i32, y as i32), explored);
+        }
+    }
+
+    while let Some(item_line) = reader.optional("item")? {
+        let fields = reader.fields(&item_line, 3)?;
+        let loc = Point::new(reader.parse(fields[0])?, reader.parse(fields[1])?);
+        let item = read_item(reader, fields[2])?;
+
+        if !map.in_bounds(loc) {
+            return Err(reader.corrupt("item is outside the map"));
+        }
+
+        map.place_item(loc, item);
+    }
+
+    Ok(map)
+}
+
+/// Reads every computer-controlled actor on a level, spawning each as an
+/// entity in the order they were saved
+fn read_actors<R>(reader: &mut Reader<R>, version: u32, entities: &mut Ecs) -> Result<(), SaveError>
+    where R: BufRead {
+    while let Some(actor_line) = reader.optional("actor")? {
+        entities.spawn(read_actor(reader, &actor_line, version, 0, Energy::new(NORMAL_SPEED))?);
+    }
+
+    Ok(())
+}
+
+/// Reads an actor along with everything it carries. Saves from before
+/// version 2 have no inventories, so actors get `default_capacity`, and
+/// saves from before version 6 have no speeds, so actors get
+/// `default_energy`. An actor saved with no speed never acts, and is given
+/// no `Energy` at all.
+fn read_actor<R>(reader: &mut Reader<R>, line: &str, version: u32, default_capacity: usize, default_energy: Energy)
+    -> Result<EntityBuilder, SaveError> where R: BufRead {
+    let field_count = match version {
+        1 => 12,
+        2..=5 => 13,
+        _ => 15,
+    };
+    let mut fields = reader.fields(line, field_count)?;
+
+    let capacity = if version < 2 {
+        default_capacity
+    } else {
+        reader.parse(fields.remove(6))?
+    };
+
+    let energy = if version < 6 {
+        Some(default_energy)
+    } else {
+        let speed = reader.parse(fields.remove(6))?;
+        let energy = reader.parse(fields.remove(6))?;
+        if speed > 0 { Some(Energy { speed: speed, energy: energy }) } else { None }
+    };
+
+    let pos = Point::new(reader.parse(fields[0])?, reader.parse(fields[1])?);
+    let health: i32 = reader.parse(fields[2])?;
+    let max_health: i32 = reader.parse(fields[3])?;
+    let attack = reader.parse(fields[4])?;
+    let defence = reader.parse(fields[5])?;
+
+    let glyph = read_glyph(reader, fields[6])?;
+    let color = Color::new(reader.parse(fields[7])?, reader.parse(fields[8])?, reader.parse(fields[9])?);
+
+    let ai = match fields[10] {
+        "none" => None,
+        "wanderer" => Some(Ai::Wanderer),
+        other if other.starts_with("hunter:") => Some(Ai::Hunter { flee_percent: reader.parse(&other[7..])? }),
+        other => return Err(reader.corrupt(&format!("unknown AI {:?}", other))),
+    };
+
+    let mut equipment = Equipment::new();
+
+    while let Some(item_line) = reader.optional("wear")? {
+        let item = read_item(reader, &item_line)?;
+
+        match equipment.wear(item) {
+            Ok(None) => {}
+            Ok(Some(_)) => return Err(reader.corrupt("actor is wearing two items in the same slot")),
+            Err(_) => return Err(reader.corrupt("actor is wearing an item which cannot be worn")),
+        }
+    }
+
+    let mut inventory = Inventory::new(capacity);
+
+    while let Some(item_line) = reader.optional("carry")? {
+        let item = read_item(reader, &item_line)?;
+
+        if inventory.add(item).is_err() {
+            return Err(reader.corrupt("actor is carrying more than it can hold"));
+        }
+    }
+
+    let mut statuses = Statuses::new();
+
+    while let Some(status_line) = reader.optional("status")? {
+        let fields = reader.fields(&status_line, 3)?;
+        statuses.add(read_status(reader, &fields)?);
+    }
+
+    let on_hit = match reader.optional("on_hit")? {
+        Some(on_hit_line) => {
+            let fields = reader.fields(&on_hit_line, 3)?;
+            Some(read_status(reader, &fields)?)
+        }
+        None => None,
+    };
+
+    let mut actor = EntityBuilder::new()
+                        .with_name(&unescape(fields[11]))
+                        .with_position(pos)
+                        .with_health(Health { current: health, base_max: max_health })
+                        .with_stats(attack, defence)
+                        .with_glyph(glyph, color)
+                        .with_inventory(inventory)
+                        .with_equipment(equipment);
+
+    if let Some(ai) = ai {
+        actor = actor.with_ai(ai);
+    }
+
+    if let Some(energy) = energy {
+        actor = actor.with_energy(energy);
+    }
+
+    if !statuses.is_empty() {
+        actor = actor.with_statuses(statuses);
+    }
+
+    if let Some(on_hit) = on_hit {
+        actor = actor.with_on_hit(on_hit);
+    }
+
+    Ok(actor)
+}
+
+fn read_item<R>(reader: &Reader<R>, line: &str) -> Result<Item, SaveError> where R: BufRead {
+    let fields = reader.fields(line, 6)?;
+
+    let kind = match fields[0] {
+        "treasure" => ItemKind::Treasure,
+        other if other.starts_with("potion:") => ItemKind::Potion { heal: reader.parse(&other[7..])? },
+        other if other.starts_with("elixir:") => {
+            let parts: Vec<&str> = other[7..].split(':').collect();
+            ItemKind::Elixir { status: read_status(reader, &parts)? }
+        }
+        other if other.starts_with("equipment:") => read_equipment(reader, &other[10..])?,
+        other => return Err(reader.corrupt(&format!("unknown item kind {:?}", other))),
+    };
+
+    let glyph = read_glyph(reader, fields[1])?;
+    let color = Color::new(reader.parse(fields[2])?, reader.parse(fields[3])?, reader.parse(fields[4])?);
+
+    Ok(Item::new(&unescape(fields[5]), glyph, color, kind))
+}
+
+/// Reads the kind, turns and strength of a status
+fn read_status<R>(reader: &Reader<R>, parts: &[&str]) -> Result<Status, SaveError> where R: BufRead {
+    if parts.len() != 3 {
+        return Err(reader.corrupt(&format!("invalid status {:?}", parts.join(" "))));
+    }
+
+    let kind = match StatusKind::from_id(parts[0]) {
+        Some(kind) => kind,
+        None => return Err(reader.corrupt(&format!("unknown status {:?}", parts[0]))),
+    };
+
+    Ok(Status::new(kind, reader.parse(parts[1])?, reader.parse(parts[2])?))
+}
+
+/// Reads the `slot:attack:defence:max_health` part of an equipment item
+fn read_equipment<R>(reader: &Reader<R>, field: &str) -> Result<ItemKind, SaveError> where R: BufRead {
+    let parts: Vec<&str> = field.split(':').collect();
+    if parts.len() != 4 {
+        return Err(reader.corrupt(&format!("invalid equipment {:?}", field)));
+    }
+
+    let slot = match Slot::all().iter().find(|&&slot| slot_name(slot) == parts[0]) {
+        Some(&slot) => slot,
+        None => return Err(reader.corrupt(&format!("unknown equipment slot {:?}", parts[0]))),
+    };
+
+    let modifiers = Modifiers::new(reader.parse(parts[1])?, reader.parse(parts[2])?, reader.parse(parts[3])?);
+    Ok(ItemKind::Equipment { slot: slot, modifiers: modifiers })
+}
+
+fn slot_name(slot: Slot) -> &'static str {
+    match slot {
+        Slot::Weapon => "weapon",
+        Slot::Armour => "armour",
+        Slot::Ring => "ring",
+    }
+}
+
+fn read_glyph<R>(reader: &Reader<R>, field: &str) -> Result<char, SaveError> where R: BufRead {
+    let glyph_code: u32 = reader.parse(field)?;
+
+    match ::std::char::from_u32(glyph_code) {
+        Some(glyph) => Ok(glyph),
+        None => Err(reader.corrupt(&format!("invalid glyph {}", glyph_code))),
+    }
+}
+
+/// Reads a save one line at a time, keeping track of the line number for errors
+struct Reader<R> {
+    lines: io::Lines<R>,
+    peeked: Option<String>,
+    line: usize,
+}
+
+impl<R> Reader<R> where R: BufRead {
+    fn new(input: R) -> Reader<R> {
+        Reader { lines: input.lines(), peeked: None, line: 0 }
+    }
+
+    fn next(&mut self) -> Result<Option<String>, SaveError> {
+        if let Some(line) = self.peeked.take() {
+            self.line += 1;
+            return Ok(Some(line));
+        }
+
+        match self.lines.next() {
+            Some(line) => {
+                self.line += 1;
+                Ok(Some(line?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&str>, SaveError> {
+        if self.peeked.is_none() {
+            if let Some(line) = self.lines.next() {
+                self.peeked = Some(line?);
+            }
+        }
+
+        Ok(self.peeked.as_ref().map(|l| l.as_str()))
+    }
+
+    /// Reads a line which must have the given tag, returning the rest of it
+    fn expect(&mut self, tag: &str) -> Result<String, SaveError> {
+        match self.next()? {
+            Some(line) => {
+                match split_tag(&line) {
+                    (t, rest) if t == tag => Ok(rest.to_string()),
+                    (t, _) => Err(self.corrupt(&format!("expected {:?}, found {:?}", tag, t))),
+                }
+            }
+            None => {
+                self.line += 1;
+                Err(self.corrupt(&format!("expected {:?}, found the end of the file", tag)))
+            }
+        }
+    }
+
+    /// Reads the next line only if it has the given tag
+    fn optional(&mut self, tag: &str) -> Result<Option<String>, SaveError> {
+        let matches = match self.peek()? {
+            Some(line) => split_tag(line).0 == tag,
+            None => false,
+        };
+
+        if matches {
+            self.expect(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Splits a record into exactly `count` fields, the last of which takes
+    /// the remainder of the line
+    fn fields<'a>(&self, rest: &'a str, count: usize) -> Result<Vec<&'a str>, SaveError> {
+        let fields: Vec<&str> = rest.splitn(count, ' ').collect();
+
+        if fields.len() == count {
+            Ok(fields)
+        } else {
+            Err(self.corrupt(&format!("expected {} fields, found {}", count, fields.len())))
+        }
+    }
+
+    fn parse<T>(&self, field: &str) -> Result<T, SaveError> where T: FromStr {
+        field.parse().map_err(|_| self.corrupt(&format!("invalid value {:?}", field)))
+    }
+
+    fn corrupt(&self, reason: &str) -> SaveError {
+        SaveError::Corrupt { line: self.line, reason: reason.to_string() }
+    }
+}
+
+fn split_tag(line: &str) -> (&str, &str) {
+    match line.find(' ') {
+        Some(i) => (&line[..i], &line[i + 1..]),
+        None => (line, ""),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write, SaveError};
+    use engine::{log, Command, Game, Rng};
+    use engine::loot;
+    use engine::status::{Status, StatusKind};
+    use util::units::Direction;
+
+    /// A game which has been played for a while, with something in each
+    /// part of the save
+    fn played_game() -> Game {
+        let mut game = Game::with_seed(2024);
+        let player = game.world.player();
+
+        let item = loot::random_item(&mut Rng::new(1));
+        game.world.entities.inventories.get_mut(player).unwrap().add(item).unwrap();
+        game.world.entities.add_status(player, Status::new(StatusKind::Regeneration, 5, 1));
+
+        for &direction in [Direction::Left, Direction::Up, Direction::Right, Direction::Down].iter().cycle().take(40) {
+            game.do_command(Command::Walk(direction));
+        }
+
+        game
+    }
+
+    fn save(game: &Game) -> String {
+        let mut out = vec![];
+        write(game, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Replaces the size and starting position on the first map line
+    fn with_map_line(save: &str, line: &str) -> String {
+        save.lines()
+            .map(|l| if l.starts_with("map ") { line } else { l })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn round_trip() {
+        let game = played_game();
+        let saved = save(&game);
+        let messages = log::LOG.with(|l| l.borrow().len());
+        let (loaded, loaded_messages) = read(saved.as_bytes()).unwrap();
+
+        assert_eq!(loaded_messages.len(), messages);
+        log::replace(loaded_messages);
+
+        assert_eq!(save(&loaded), saved);
+        assert_eq!(loaded.turn, game.turn);
+        assert_eq!(loaded.time, game.time);
+        assert_eq!(loaded.world.player_pos(), game.world.player_pos());
+        assert_eq!(loaded.world.entities.entities().len(), game.world.entities.entities().len());
+        assert!(loaded.world.map.tiles == game.world.map.tiles);
+    }
+
+    #[test]
+    fn failed_load_leaves_the_log_alone() {
+        let saved = save(&played_game());
+        log::combat("Still here.");
+
+        assert!(read(&saved.as_bytes()[..saved.len() / 2]).is_err());
+        assert_eq!(log::LOG.with(|l| l.borrow().items().next().map(|m| m.text())), Some("Still here.".to_string()));
+    }
+
+    #[test]
+    fn rejects_empty_map() {
+        let saved = with_map_line(&save(&played_game()), "map 0 0 0 0");
+
+        match read(saved.as_bytes()) {
+            Err(SaveError::Corrupt { .. }) => {}
+            other => panic!("expected a corrupt save, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_starting_position_outside_map() {
+        let game = played_game();
+        let line = format!("map {} {} {} 0", game.world.map.width(), game.world.map.height(), game.world.map.width());
+        let saved = with_map_line(&save(&game), &line);
+
+        match read(saved.as_bytes()) {
+            Err(SaveError::Corrupt { .. }) => {}
+            other => panic!("expected a corrupt save, got {:?}", other.map(|_| ())),
+        }
+    }
+}
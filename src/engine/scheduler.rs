@@ -0,0 +1,156 @@
+//! Deciding who acts when
+//!
+//! Game time passes in ticks. Every tick, each entity with `Energy` gains
+//! energy equal to its speed, and once it has `ACTION_THRESHOLD` it may act.
+//! Acting spends energy according to what was done, so an entity twice as
+//! fast as normal acts twice for every action of a normal one, and one half
+//! as fast acts every other time.
+//!
+//! The player is scheduled the same way. `Game::step` lets time pass after
+//! each command until the player has the energy to act again.
+
+use engine::ecs::{Ecs, Entity};
+
+/// The speed of the player and of most monsters
+pub const NORMAL_SPEED: i32 = 10;
+
+/// The energy an entity needs before it can act
+pub const ACTION_THRESHOLD: i32 = 100;
+
+/// The number of ticks a normal action takes at normal speed
+pub const TICKS_PER_TURN: u64 = (ACTION_THRESHOLD / NORMAL_SPEED) as u64;
+
+/// The kinds of action, which each take a different amount of time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    /// Walking to a neighbouring tile
+    Move,
+    /// Swinging at something, which takes a little longer than a step
+    Attack,
+    /// Standing still, which lets the entity react again sooner
+    Wait,
+    /// Picking up, dropping or using an item, or taking the stairs
+    Interact,
+}
+
+impl ActionKind {
+    /// The energy the action uses up
+    pub fn cost(&self) -> i32 {
+        match *self {
+            ActionKind::Move => 100,
+            ActionKind::Attack => 120,
+            ActionKind::Wait => 50,
+            ActionKind::Interact => 100,
+        }
+    }
+}
+
+/// Returns true if an entity has the energy to act. Entities without
+/// `Energy` are never scheduled.
+pub fn is_ready(entities: &Ecs, entity: Entity) -> bool {
+    entities.energies.get(entity).map_or(false, |e| e.energy >= ACTION_THRESHOLD)
+}
+
+/// Uses up the energy an action costs
+pub fn spend(entities: &mut Ecs, entity: Entity, action: ActionKind) {
+    if let Some(energy) = entities.energies.get_mut(entity) {
+        energy.energy -= action.cost();
+    }
+}
+
+/// Gives every entity with `Energy` one tick's worth, at its speed
+/// including haste
+pub fn tick(entities: &mut Ecs) {
+    for entity in entities.energies.entities() {
+        let speed = entities.speed(entity);
+
+        if let Some(energy) = entities.energies.get_mut(entity) {
+            energy.energy += speed;
+        }
+    }
+}
+
+/// Describes a game time in turns of a normal-speed entity, to one decimal
+/// place
+pub fn format_time(time: u64) -> String {
+    let tenths = time * 10 / TICKS_PER_TURN;
+    format!("{}.{}", tenths / 10, tenths % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ready, spend, tick, ActionKind, NORMAL_SPEED, TICKS_PER_TURN};
+    use engine::components::Energy;
+    use engine::ecs::{Ecs, Entity, EntityBuilder};
+    use engine::status::{Status, StatusKind};
+
+    /// Lets `ticks` ticks pass, with every entity which is ready taking the
+    /// action given for it, and counts the actions each one took
+    fn run(entities: &mut Ecs, actors: &[(Entity, ActionKind)], ticks: u64) -> Vec<u32> {
+        let mut counts = vec![0; actors.len()];
+
+        for _ in 0..ticks {
+            tick(entities);
+
+            for (i, &(entity, action)) in actors.iter().enumerate() {
+                if is_ready(entities, entity) {
+                    spend(entities, entity, action);
+                    counts[i] += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    fn spawn(entities: &mut Ecs, speed: i32) -> Entity {
+        entities.spawn(EntityBuilder::new().with_energy(Energy::new(speed)))
+    }
+
+    #[test]
+    fn speed_sets_how_often_entities_act() {
+        let mut entities = Ecs::new();
+        let normal = spawn(&mut entities, NORMAL_SPEED);
+        let fast = spawn(&mut entities, NORMAL_SPEED * 2);
+        let slow = spawn(&mut entities, NORMAL_SPEED / 2);
+
+        let actors = [(normal, ActionKind::Move), (fast, ActionKind::Move), (slow, ActionKind::Move)];
+        let counts = run(&mut entities, &actors, TICKS_PER_TURN * 10);
+
+        assert_eq!(counts, vec![10, 20, 5]);
+    }
+
+    #[test]
+    fn actions_cost_different_amounts() {
+        let mut entities = Ecs::new();
+        let mover = spawn(&mut entities, NORMAL_SPEED);
+        let attacker = spawn(&mut entities, NORMAL_SPEED);
+        let waiter = spawn(&mut entities, NORMAL_SPEED);
+
+        let actors = [(mover, ActionKind::Move), (attacker, ActionKind::Attack), (waiter, ActionKind::Wait)];
+        let counts = run(&mut entities, &actors, TICKS_PER_TURN * 12);
+
+        // Waiting leaves half the energy, so after the first wait another
+        // comes every half turn
+        assert_eq!(counts, vec![12, 10, 23]);
+    }
+
+    #[test]
+    fn haste_doubles_speed() {
+        let mut entities = Ecs::new();
+        let hasted = spawn(&mut entities, NORMAL_SPEED);
+        entities.add_status(hasted, Status::new(StatusKind::Haste, 100, 1));
+
+        let counts = run(&mut entities, &[(hasted, ActionKind::Move)], TICKS_PER_TURN * 10);
+
+        assert_eq!(counts, vec![20]);
+    }
+
+    #[test]
+    fn entities_without_energy_never_act() {
+        let mut entities = Ecs::new();
+        let statue = entities.spawn(EntityBuilder::new());
+
+        assert_eq!(run(&mut entities, &[(statue, ActionKind::Move)], 100), vec![0]);
+    }
+}
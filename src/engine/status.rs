@@ -0,0 +1,286 @@
+//! Status effects which last for a number of turns
+//!
+//! Statuses tick once per turn of game time rather than once per action,
+//! so they last equally long whoever they are on, however fast. Giving an
+//! entity a status it already has combines the two according to the
+//! kind's `Stacking` rule.
+
+use util::units::Color;
+
+use std::slice;
+
+/// A kind of status effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusKind {
+    /// Loses `strength` health every turn
+    Poison,
+    /// Gains `strength` health every turn, up to the maximum
+    Regeneration,
+    /// Acts twice as often
+    Haste,
+    /// Sometimes stumbles in a random direction instead of the one chosen
+    Confusion,
+}
+
+/// How a status combines with another of the same kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// Strengths add up, lasting as long as the longer of the two
+    Intensify,
+    /// The stronger and longer of the two are kept
+    Refresh,
+    /// Durations add up, keeping the stronger of the two
+    Extend,
+}
+
+/// What giving an entity a status did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Added {
+    /// The entity did not have the status before
+    New,
+    /// The status was combined with one the entity already had, making it
+    /// stronger or longer
+    Stacked,
+    /// Nothing changed, because the status had no turns or was no stronger
+    /// or longer than the one already there
+    Unchanged,
+}
+
+static KINDS: [StatusKind; 4] = [StatusKind::Poison, StatusKind::Regeneration, StatusKind::Haste, StatusKind::Confusion];
+
+impl StatusKind {
+    pub fn all() -> &'static [StatusKind; 4] {
+        &KINDS
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            StatusKind::Poison => "Poison",
+            StatusKind::Regeneration => "Regeneration",
+            StatusKind::Haste => "Haste",
+            StatusKind::Confusion => "Confusion",
+        }
+    }
+
+    /// The character and colour shown beside the status in the info panel
+    pub fn icon(&self) -> (char, Color) {
+        match *self {
+            StatusKind::Poison => ('!', Color::new(80, 200, 60)),
+            StatusKind::Regeneration => ('+', Color::new(220, 60, 80)),
+            StatusKind::Haste => ('>', Color::new(230, 210, 60)),
+            StatusKind::Confusion => ('?', Color::new(200, 100, 220)),
+        }
+    }
+
+    pub fn stacking(&self) -> Stacking {
+        match *self {
+            StatusKind::Poison => Stacking::Intensify,
+            StatusKind::Regeneration | StatusKind::Haste => Stacking::Refresh,
+            StatusKind::Confusion => Stacking::Extend,
+        }
+    }
+
+    /// Told to the player when the status first takes hold of them
+    pub fn start_message(&self) -> &'static str {
+        match *self {
+            StatusKind::Poison => "You are poisoned!",
+            StatusKind::Regeneration => "Your wounds begin to close.",
+            StatusKind::Haste => "You feel yourself speed up.",
+            StatusKind::Confusion => "Your head spins.",
+        }
+    }
+
+    /// Told to the player when the status wears off
+    pub fn end_message(&self) -> &'static str {
+        match *self {
+            StatusKind::Poison => "The poison wears off.",
+            StatusKind::Regeneration => "Your wounds stop closing.",
+            StatusKind::Haste => "You slow down.",
+            StatusKind::Confusion => "Your head clears.",
+        }
+    }
+
+    /// Used in save files
+    pub fn id(&self) -> &'static str {
+        match *self {
+            StatusKind::Poison => "poison",
+            StatusKind::Regeneration => "regeneration",
+            StatusKind::Haste => "haste",
+            StatusKind::Confusion => "confusion",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<StatusKind> {
+        KINDS.iter().find(|k| k.id() == id).cloned()
+    }
+}
+
+/// A status effect and how long it has left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Status {
+    pub kind: StatusKind,
+    /// The number of turns left before it wears off
+    pub turns: u32,
+    /// How strong the effect is, for those which have a strength
+    pub strength: i32,
+}
+
+impl Status {
+    pub fn new(kind: StatusKind, turns: u32, strength: i32) -> Status {
+        Status { kind: kind, turns: turns, strength: strength }
+    }
+
+    /// Describes the status for the info panel, as in "Poison x2 (5)"
+    pub fn label(&self) -> String {
+        match self.kind {
+            StatusKind::Poison | StatusKind::Regeneration => {
+                format!("{} x{} ({})", self.kind.name(), self.strength, self.turns)
+            }
+            StatusKind::Haste | StatusKind::Confusion => format!("{} ({})", self.kind.name(), self.turns),
+        }
+    }
+
+    /// Combines another status of the same kind into this one, returning
+    /// whether that changed anything
+    fn stack(&mut self, other: Status) -> bool {
+        let before = *self;
+
+        match self.kind.stacking() {
+            Stacking::Intensify => {
+                self.strength = self.strength.saturating_add(other.strength);
+                self.turns = self.turns.max(other.turns);
+            }
+            Stacking::Refresh => {
+                self.strength = self.strength.max(other.strength);
+                self.turns = self.turns.max(other.turns);
+            }
+            Stacking::Extend => {
+                self.strength = self.strength.max(other.strength);
+                self.turns = self.turns.saturating_add(other.turns);
+            }
+        }
+
+        *self != before
+    }
+}
+
+/// Every status effect on an entity, in the order they took hold
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statuses {
+    statuses: Vec<Status>,
+}
+
+impl Statuses {
+    pub fn new() -> Statuses {
+        Statuses::default()
+    }
+
+    /// Adds a status, stacking it with any of the same kind already there.
+    /// A status with no turns left is ignored.
+    pub fn add(&mut self, status: Status) -> Added {
+        if status.turns == 0 {
+            return Added::Unchanged;
+        }
+
+        match self.statuses.iter_mut().find(|s| s.kind == status.kind) {
+            Some(existing) => if existing.stack(status) { Added::Stacked } else { Added::Unchanged },
+            None => {
+                self.statuses.push(status);
+                Added::New
+            }
+        }
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&Status> {
+        self.statuses.iter().find(|s| s.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Status> {
+        self.statuses.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+
+    /// Counts down a turn on every status, removing and returning those
+    /// which have worn off
+    pub fn tick(&mut self) -> Vec<StatusKind> {
+        let mut expired = vec![];
+
+        for status in self.statuses.iter_mut() {
+            status.turns = status.turns.saturating_sub(1);
+
+            if status.turns == 0 {
+                expired.push(status.kind);
+            }
+        }
+
+        self.statuses.retain(|s| s.turns > 0);
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Added, Status, StatusKind, Statuses};
+
+    fn with(status: Status) -> Statuses {
+        let mut statuses = Statuses::new();
+        assert_eq!(statuses.add(status), Added::New);
+        statuses
+    }
+
+    #[test]
+    fn stacks_by_kind() {
+        let mut poison = with(Status::new(StatusKind::Poison, 3, 1));
+        assert_eq!(poison.add(Status::new(StatusKind::Poison, 5, 2)), Added::Stacked);
+        assert_eq!(poison.get(StatusKind::Poison), Some(&Status::new(StatusKind::Poison, 5, 3)));
+
+        let mut haste = with(Status::new(StatusKind::Haste, 10, 1));
+        assert_eq!(haste.add(Status::new(StatusKind::Haste, 4, 1)), Added::Unchanged);
+        assert_eq!(haste.add(Status::new(StatusKind::Haste, 12, 1)), Added::Stacked);
+        assert_eq!(haste.get(StatusKind::Haste), Some(&Status::new(StatusKind::Haste, 12, 1)));
+
+        let mut confusion = with(Status::new(StatusKind::Confusion, 3, 1));
+        assert_eq!(confusion.add(Status::new(StatusKind::Confusion, 4, 1)), Added::Stacked);
+        assert_eq!(confusion.get(StatusKind::Confusion), Some(&Status::new(StatusKind::Confusion, 7, 1)));
+    }
+
+    #[test]
+    fn ignores_statuses_without_turns() {
+        let mut statuses = Statuses::new();
+        assert_eq!(statuses.add(Status::new(StatusKind::Poison, 0, 1)), Added::Unchanged);
+        assert!(statuses.is_empty());
+
+        let mut confusion = with(Status::new(StatusKind::Confusion, 3, 1));
+        assert_eq!(confusion.add(Status::new(StatusKind::Confusion, 0, 1)), Added::Unchanged);
+        assert_eq!(confusion.get(StatusKind::Confusion).map(|s| s.turns), Some(3));
+    }
+
+    #[test]
+    fn extending_saturates() {
+        let mut confusion = with(Status::new(StatusKind::Confusion, u32::max_value() - 1, 1));
+        confusion.add(Status::new(StatusKind::Confusion, 5, 1));
+
+        assert_eq!(confusion.get(StatusKind::Confusion).map(|s| s.turns), Some(u32::max_value()));
+    }
+
+    #[test]
+    fn ticks_down_and_expires() {
+        let mut statuses = with(Status::new(StatusKind::Poison, 1, 1));
+        statuses.add(Status::new(StatusKind::Haste, 2, 1));
+
+        assert_eq!(statuses.tick(), vec![StatusKind::Poison]);
+        assert!(!statuses.has(StatusKind::Poison));
+        assert_eq!(statuses.get(StatusKind::Haste).map(|s| s.turns), Some(1));
+
+        assert_eq!(statuses.tick(), vec![StatusKind::Haste]);
+        assert!(statuses.is_empty());
+        assert_eq!(statuses.tick(), vec![]);
+    }
+}
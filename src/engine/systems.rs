@@ -0,0 +1,94 @@
+//! Systems, which update every entity with the components they care about
+//!
+//! `Game::step` runs them each tick until the player can act again.
+
+use engine::{Rng, World};
+use engine::ai::{Action, PlayerMaps};
+use engine::log;
+use engine::scheduler::{self, ActionKind};
+use engine::status::StatusKind;
+
+/// Removes every entity which has died, other than the one the player
+/// controls, which stays put so that the game can tell it is over
+pub fn remove_dead(world: &mut World) {
+    for entity in world.entities.healths.entities() {
+        if world.entities.is_dead(entity) && !world.entities.controlled.contains(entity) {
+            log::combat(&format!("The {} dies.", log::escape(world.entities.name(entity))));
+            world.entities.destroy(entity);
+        }
+    }
+}
+
+/// Applies every status effect for one turn, counting down how long each
+/// has left
+pub fn tick_statuses(world: &mut World) {
+    for entity in world.entities.statuses.entities() {
+        let is_player = world.entities.controlled.contains(entity);
+        let was_dead = world.entities.is_dead(entity);
+
+        let statuses: Vec<_> = match world.entities.statuses.get(entity) {
+            Some(statuses) => statuses.iter().cloned().collect(),
+            None => continue,
+        };
+
+        for status in statuses {
+            match status.kind {
+                StatusKind::Poison => {
+                    world.entities.change_health(entity, -status.strength);
+
+                    if is_player {
+                        log::combat(&format!("The poison burns for {{red:{}}} damage.", status.strength));
+                    }
+                }
+                StatusKind::Regeneration => world.entities.change_health(entity, status.strength),
+                StatusKind::Haste | StatusKind::Confusion => {}
+            }
+        }
+
+        let expired = world.entities.statuses.get_mut(entity).map_or(vec![], |s| s.tick());
+
+        if is_player {
+            for kind in expired {
+                log::combat(kind.end_message());
+            }
+
+            if !was_dead && world.entities.is_dead(entity) {
+                log::combat("{red:You die...}");
+            }
+        }
+    }
+}
+
+/// Gives every entity its energy for one tick
+pub fn gain_energy(world: &mut World) {
+    scheduler::tick(&mut world.entities);
+}
+
+/// Lets every entity with an `Ai` and the energy to act take its turn
+pub fn take_turns(world: &mut World, rng: &mut Rng) {
+    let mut maps = PlayerMaps::new();
+
+    for entity in world.entities.ais.entities() {
+        if world.is_player_dead() {
+            break;
+        }
+
+        if !scheduler::is_ready(&world.entities, entity) {
+            continue;
+        }
+
+        let ai = match world.entities.ais.get(entity) {
+            Some(&ai) => ai,
+            None => continue,
+        };
+
+        // Bumping into a wall still uses up the turn, or the entity would
+        // keep trying every tick
+        let action = match ai.choose_action(entity, world, &mut maps, rng) {
+            Action::Walk(direction) => world.walk_entity(entity, direction, rng).unwrap_or(ActionKind::Wait),
+            Action::Wait => ActionKind::Wait,
+        };
+
+        scheduler::spend(&mut world.entities, entity, action);
+    }
+}
@@ -1,48 +1,438 @@
+//! Tile types, defined in a data file rather than in code
+//!
+//! Every kind of tile is described by a `TileDef` in the current `TileSet`,
+//! and maps refer to them by a small interned id, `Tile`. The built-in tile
+//! set is compiled from `assets/tiles.txt`, and can be replaced at startup
+//! with `load_file` so that new tiles need no code changes.
+
 use util::FromChar;
+use util::units::Color;
 
+use std::cell::RefCell;
+use std::error::Error;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+thread_local!(static TILES: RefCell<TileSet> = RefCell::new(TileSet::builtin()));
+
+/// The tile definitions compiled into the game
+static BUILTIN_TILES: &'static str = include_str!("../../assets/tiles.txt");
 
-#[derive(Clone, Copy)]
-pub enum Tile {
-    Empty,
-    Wall,
-    Floor,
-    Grass,
+/// Tiles which the engine itself creates, and so must always be defined
+static REQUIRED_TILES: [&'static str; 4] = ["floor", "wall", "stairs_down", "stairs_up"];
+
+/// Replaces the current tile set with one loaded from a file.
+///
+/// Tiles are looked up by id, so this must happen before any maps are made.
+pub fn load_file<P>(path: P) -> Result<(), TileSetError> where P: AsRef<Path> {
+    let tile_set = TileSet::from_file(path)?;
+    TILES.with(|t| *t.borrow_mut() = tile_set);
+    Ok(())
 }
 
+/// A kind of tile, as an index into the current `TileSet`
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile(u16);
+
 impl Tile {
+    /// Looks up a tile by name
+    pub fn named(name: &str) -> Option<Tile> {
+        TILES.with(|t| t.borrow().find(|def| def.name == name))
+    }
+
+    /// The tile used for open ground
+    pub fn floor() -> Tile {
+        Tile::named("floor").expect("The tile set has no floor tile")
+    }
+
+    /// The tile used for solid rock
+    pub fn wall() -> Tile {
+        Tile::named("wall").expect("The tile set has no wall tile")
+    }
+
+    /// The tile leading to the level below
+    pub fn stairs_down() -> Tile {
+        Tile::named("stairs_down").expect("The tile set has no stairs_down tile")
+    }
+
+    /// The tile leading to the level above
+    pub fn stairs_up() -> Tile {
+        Tile::named("stairs_up").expect("The tile set has no stairs_up tile")
+    }
+
+    pub fn name(&self) -> String {
+        self.with_def(|def| def.name.clone())
+    }
+
+    /// Returns the character representing the tile in a map file
+    pub fn to_char(&self) -> char {
+        self.with_def(|def| def.map_char)
+    }
+
+    /// Returns the character drawn on screen for the tile
+    pub fn glyph(&self) -> char {
+        self.with_def(|def| def.glyph)
+    }
+
+    pub fn foreground(&self) -> Color {
+        self.with_def(|def| def.foreground)
+    }
+
+    pub fn background(&self) -> Color {
+        self.with_def(|def| def.background)
+    }
+
     pub fn is_walkable(&self) -> bool {
-        match *self {
-            Tile::Empty => true,
-            Tile::Wall => false,
-            Tile::Floor => true,
-            Tile::Grass => true,
-        }
+        self.with_def(|def| def.walkable)
+    }
+
+    /// Returns true if actors can see through the tile
+    pub fn is_transparent(&self) -> bool {
+        self.with_def(|def| def.transparent)
+    }
+
+    fn with_def<F, T>(&self, f: F) -> T where F: FnOnce(&TileDef) -> T {
+        TILES.with(|t| f(&t.borrow().defs[self.0 as usize]))
+    }
+}
+
+/// The error returned when a character does not correspond to any tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidTileChar(pub char);
+
+impl fmt::Display for InvalidTileChar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no valid tile for character {:?}", self.0)
     }
 }
 
 impl FromChar for Tile {
-    type Ret = Tile;
+    type Err = InvalidTileChar;
 
-    fn from_char(c: char) -> Tile {
-        match c {
-            ' ' => Tile::Empty,
-            '.' | '@' => Tile::Floor,
-            '#' => Tile::Wall,
-            _ => panic!("No valid tile for character {}", c)
-        }
+    fn from_char(c: char) -> Result<Tile, InvalidTileChar> {
+        TILES.with(|t| t.borrow().find(|def| def.map_char == c))
+             .ok_or(InvalidTileChar(c))
     }
 }
 
 impl fmt::Debug for Tile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let repr = match *self {
-            Tile::Empty => ' ',
-            Tile::Wall => '#',
-            Tile::Floor => '.',
-            _ => ' ',
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// Everything there is to know about one kind of tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileDef {
+    pub name: String,
+    /// The character used for the tile in map files
+    pub map_char: char,
+    /// The character drawn on screen
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color,
+    pub walkable: bool,
+    pub transparent: bool,
+}
+
+/// A collection of tile definitions
+#[derive(Clone, Debug)]
+pub struct TileSet {
+    defs: Vec<TileDef>,
+}
+
+impl TileSet {
+    /// The tile set compiled into the game
+    pub fn builtin() -> TileSet {
+        TileSet::from_string(BUILTIN_TILES).expect("The built-in tile definitions are invalid")
+    }
+
+    pub fn from_file<P>(path: P) -> Result<TileSet, TileSetError> where P: AsRef<Path> {
+        let mut file = File::open(path)?;
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        TileSet::from_string(&contents)
+    }
+
+    /// Parses tile definitions.
+    ///
+    /// Each tile begins with its name in square brackets, followed by one
+    /// `key = value` line per property. Blank lines and lines starting with
+    /// `#` are ignored.
+    pub fn from_string(s: &str) -> Result<TileSet, TileSetError> {
+        let mut defs: Vec<TileDef> = vec![];
+        let mut current: Option<PartialDef> = None;
+
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some(partial) = current.take() {
+                    push_def(&mut defs, partial)?;
+                }
+
+                let name = line[1..line.len() - 1].trim();
+                if name.is_empty() {
+                    return Err(syntax(line_number, "tile name is empty"));
+                }
+
+                current = Some(PartialDef::new(name, line_number));
+                continue;
+            }
+
+            let partial = match current {
+                Some(ref mut partial) => partial,
+                None => return Err(syntax(line_number, "property given before any tile name")),
+            };
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(syntax(line_number, "expected `key = value`")),
+            };
+
+            match key {
+                "char" => partial.map_char = Some(parse_char(line_number, value)?),
+                "glyph" => partial.glyph = Some(parse_char(line_number, value)?),
+                "foreground" => partial.foreground = Some(parse_color(line_number, value)?),
+                "background" => partial.background = Some(parse_color(line_number, value)?),
+                "walkable" => partial.walkable = Some(parse_bool(line_number, value)?),
+                "transparent" => partial.transparent = Some(parse_bool(line_number, value)?),
+                _ => return Err(syntax(line_number, &format!("unknown property `{}`", key))),
+            }
+        }
+
+        if let Some(partial) = current.take() {
+            push_def(&mut defs, partial)?;
+        }
+
+        for name in REQUIRED_TILES.iter() {
+            if !defs.iter().any(|def| def.name == *name) {
+                return Err(TileSetError::MissingTile { name: name });
+            }
+        }
+
+        if defs.len() > u16::max_value() as usize {
+            return Err(TileSetError::TooManyTiles);
+        }
+
+        Ok(TileSet { defs: defs })
+    }
+
+    /// Returns the definition for a tile
+    pub fn get(&self, tile: Tile) -> &TileDef {
+        &self.defs[tile.0 as usize]
+    }
+
+    fn find<F>(&self, predicate: F) -> Option<Tile> where F: Fn(&TileDef) -> bool {
+        self.defs.iter().position(predicate).map(|i| Tile(i as u16))
+    }
+}
+
+/// A tile definition which is still being read
+struct PartialDef {
+    name: String,
+    line: usize,
+    map_char: Option<char>,
+    glyph: Option<char>,
+    foreground: Option<Color>,
+    background: Option<Color>,
+    walkable: Option<bool>,
+    transparent: Option<bool>,
+}
+
+impl PartialDef {
+    fn new(name: &str, line: usize) -> PartialDef {
+        PartialDef {
+            name: name.to_string(),
+            line: line,
+            map_char: None,
+            glyph: None,
+            foreground: None,
+            background: None,
+            walkable: None,
+            transparent: None,
+        }
+    }
+
+    fn finish(self) -> Result<TileDef, TileSetError> {
+        let missing = |property| TileSetError::MissingProperty {
+            line: self.line,
+            tile: self.name.clone(),
+            property: property,
         };
 
-        write!(f, "{}", repr)
+        Ok(TileDef {
+            map_char: self.map_char.ok_or_else(|| missing("char"))?,
+            glyph: self.glyph.ok_or_else(|| missing("glyph"))?,
+            foreground: self.foreground.ok_or_else(|| missing("foreground"))?,
+            background: self.background.ok_or_else(|| missing("background"))?,
+            walkable: self.walkable.ok_or_else(|| missing("walkable"))?,
+            transparent: self.transparent.ok_or_else(|| missing("transparent"))?,
+            name: self.name.clone(),
+        })
+    }
+}
+
+fn push_def(defs: &mut Vec<TileDef>, partial: PartialDef) -> Result<(), TileSetError> {
+    let line = partial.line;
+    let def = partial.finish()?;
+
+    if defs.iter().any(|other| other.name == def.name) {
+        return Err(syntax(line, &format!("tile `{}` is defined twice", def.name)));
+    }
+
+    // '@' marks the starting position in map files, so it can never be a tile
+    if def.map_char == '@' || defs.iter().any(|other| other.map_char == def.map_char) {
+        return Err(syntax(line, &format!("map character {:?} is already in use", def.map_char)));
+    }
+
+    defs.push(def);
+    Ok(())
+}
+
+fn parse_char(line: usize, value: &str) -> Result<char, TileSetError> {
+    let unquoted = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+
+    let mut chars = unquoted.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(syntax(line, &format!("expected a single character, found `{}`", value))),
+    }
+}
+
+fn parse_color(line: usize, value: &str) -> Result<Color, TileSetError> {
+    let components: Vec<u8> = value.split_whitespace().filter_map(|c| c.parse().ok()).collect();
+
+    if components.len() == 3 && value.split_whitespace().count() == 3 {
+        Ok(Color::new(components[0], components[1], components[2]))
+    } else {
+        Err(syntax(line, &format!("expected a colour as `red green blue`, found `{}`", value)))
+    }
+}
+
+fn parse_bool(line: usize, value: &str) -> Result<bool, TileSetError> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(syntax(line, &format!("expected `true` or `false`, found `{}`", value))),
+    }
+}
+
+fn syntax(line: usize, reason: &str) -> TileSetError {
+    TileSetError::Syntax { line: line, reason: reason.to_string() }
+}
+
+/// An error encountered while loading tile definitions
+#[derive(Debug)]
+pub enum TileSetError {
+    /// The definitions could not be read
+    Io(io::Error),
+    /// A line could not be understood
+    Syntax { line: usize, reason: String },
+    /// A tile did not define one of its properties
+    MissingProperty { line: usize, tile: String, property: &'static str },
+    /// A tile the engine relies on was not defined
+    MissingTile { name: &'static str },
+    /// There were more tiles than can be given an id
+    TooManyTiles,
+}
+
+impl fmt::Display for TileSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TileSetError::Io(ref err) => write!(f, "could not read tile definitions: {}", err),
+            TileSetError::Syntax { line, ref reason } => write!(f, "line {}: {}", line, reason),
+            TileSetError::MissingProperty { line, ref tile, property } =>
+                write!(f, "line {}: tile `{}` does not define `{}`", line, tile, property),
+            TileSetError::MissingTile { name } => write!(f, "no `{}` tile is defined", name),
+            TileSetError::TooManyTiles => write!(f, "too many tiles are defined"),
+        }
+    }
+}
+
+impl Error for TileSetError {}
+
+impl From<io::Error> for TileSetError {
+    fn from(err: io::Error) -> TileSetError {
+        TileSetError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TileSet, TileSetError, BUILTIN_TILES, REQUIRED_TILES};
+
+    fn tile(name: &str, map_char: char) -> String {
+        format!("[{}]\nchar = '{}'\nglyph = x\nforeground = 1 2 3\nbackground = 4 5 6\n\
+                 walkable = true\ntransparent = false\n\n", name, map_char)
+    }
+
+    /// Every tile the engine needs, as in `floor = .`
+    fn required() -> String {
+        REQUIRED_TILES.iter().zip(".#><".chars()).map(|(name, c)| tile(name, c)).collect()
+    }
+
+    #[test]
+    fn builtin_tiles_parse() {
+        let tiles = TileSet::from_string(BUILTIN_TILES).unwrap();
+
+        for name in REQUIRED_TILES.iter() {
+            assert!(tiles.find(|def| def.name == *name).is_some(), "no {} tile", name);
+        }
+    }
+
+    #[test]
+    fn the_required_tiles_are_enough() {
+        assert!(TileSet::from_string(&required()).is_ok());
+    }
+
+    #[test]
+    fn rejects_missing_required_tiles() {
+        for name in REQUIRED_TILES.iter() {
+            let s: String = REQUIRED_TILES.iter().zip(".#><".chars())
+                                          .filter(|&(other, _)| other != name)
+                                          .map(|(other, c)| tile(other, c))
+                                          .collect();
+
+            match TileSet::from_string(&s) {
+                Err(TileSetError::MissingTile { name: missing }) => assert_eq!(missing, *name),
+                other => panic!("expected {} to be missing, got {:?}", name, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_a_map_character_in_use() {
+        let s = required() + &tile("water", '.');
+
+        match TileSet::from_string(&s) {
+            Err(TileSetError::Syntax { line: 33, ref reason }) => {
+                assert!(reason.contains("already in use"), "{}", reason);
+            }
+            other => panic!("expected a syntax error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_the_starting_position_character() {
+        let s = required() + &tile("start", '@');
+
+        match TileSet::from_string(&s) {
+            Err(TileSetError::Syntax { ref reason, .. }) => assert!(reason.contains("'@'"), "{}", reason),
+            other => panic!("expected a syntax error, got {:?}", other.map(|_| ())),
+        }
     }
 }
@@ -1,26 +1,472 @@
-use engine::{Actor, Map};
-use util::units::Direction;
+use engine::{Item, ItemKind, Level, Map, Rng, Slot, Tile};
+use engine::combat;
+use engine::components::{Energy, Equipment, Health, Inventory, Position};
+use engine::ecs::{Ecs, Entity, EntityBuilder};
+use engine::level::{self, ITEM_COUNT, MONSTER_COUNT};
+use engine::log;
+use engine::loot;
+use engine::monsters;
+use engine::scheduler::{ActionKind, NORMAL_SPEED};
+use engine::status::{Added, Stacking, Status, StatusKind};
+use util::units::{Color, Direction, Point};
 
+use std::cmp;
+use std::mem;
+
+/// How far the player can see, in tiles
+const FOV_RADIUS: i32 = 10;
+
+/// How many items the player can carry
+pub const PLAYER_CAPACITY: usize = 26;
+
+/// Everything in the dungeon. The fields `entities` and `map` belong to the
+/// level the player is on, while every other level visited is kept in
+/// `levels` until the player returns. The player is the entity with the
+/// `Controlled` component, and moves between the levels' entities when
+/// taking the stairs.
 pub struct World {
-    pub player: Actor,
-    pub actors: Vec<Actor>,
+    pub entities: Ecs,
     pub map: Map,
+    /// How many levels down the player is, starting from 1
+    pub depth: u32,
+    /// The levels of the dungeon by depth, starting from 1. The player's
+    /// current level and any level never visited are `None`.
+    pub levels: Vec<Option<Level>>,
 }
 
 impl World {
-    pub fn new() -> World {
-        let map = Map::new();
-
-        World {
-            player: Actor::new("Player", map.starting_position, 100),
-            actors: Vec::new(),
+    pub fn new(map: Map) -> World {
+        let mut world = World {
+            entities: Ecs::new(),
             map: map,
+            depth: 1,
+            levels: Vec::new(),
+        };
+
+        let start = world.map.starting_position;
+        world.entities.spawn(player(start));
+        world.update_fov();
+        world
+    }
+
+    /// The entity the player controls
+    pub fn player(&self) -> Entity {
+        self.entities.controlled_entity().expect("The world has no player")
+    }
+
+    /// Where the player is standing
+    pub fn player_pos(&self) -> Point {
+        self.entities.position(self.player()).expect("The player has no position")
+    }
+
+    pub fn is_player_dead(&self) -> bool {
+        self.entities.is_dead(self.player())
+    }
+
+    /// Moves the player one step, attacking anything standing in the way.
+    /// Walking in the `Wait` direction passes the turn without moving.
+    ///
+    /// Returns what the player did, or `None` if the way is blocked and they
+    /// did nothing.
+    pub fn walk(&mut self, direction: Direction, rng: &mut Rng) -> Option<ActionKind> {
+        let player = self.player();
+        self.walk_entity(player, direction, rng)
+    }
+
+    /// Moves an entity one step. Walking into an entity on the other side,
+    /// that is the player for a monster or a monster for the player,
+    /// attacks it. Walking in the `Wait` direction does nothing. A confused
+    /// entity goes in a random direction half of the time.
+    ///
+    /// Returns what the entity did, or `None` if the way is blocked and it
+    /// did nothing.
+    pub fn walk_entity(&mut self, entity: Entity, direction: Direction, rng: &mut Rng) -> Option<ActionKind> {
+        if direction == Direction::Wait { return Some(ActionKind::Wait); }
+
+        let mut direction = direction;
+        let stumbled = self.entities.has_status(entity, StatusKind::Confusion) && rng.chance(50);
+
+        if stumbled {
+            direction = *rng.choose(Direction::all()).expect("There are always directions");
+
+            if self.entities.controlled.contains(entity) {
+                log::combat("You stumble around in confusion.");
+            }
+        }
+
+        let pos = match self.entities.position(entity) {
+            Some(pos) => pos,
+            None => return None,
+        };
+        let target = pos.move_dir(direction);
+
+        if let Some(other) = self.entities.entity_at(target) {
+            let controlled = &self.entities.controlled;
+            if controlled.contains(entity) != controlled.contains(other) && self.entities.healths.contains(other) {
+                self.attack(entity, other);
+                return Some(ActionKind::Attack);
+            }
+        }
+
+        // Stumbling into a wall still wastes the turn
+        if !self.is_free(target) {
+            return if stumbled { Some(ActionKind::Move) } else { None };
+        }
+
+        self.entities.positions.insert(entity, Position(target));
+
+        if self.entities.controlled.contains(entity) {
+            self.update_fov();
+        }
+
+        Some(ActionKind::Move)
+    }
+
+    /// Takes the stairs down from where the player is standing, generating
+    /// the level below if nobody has been there yet.
+    ///
+    /// Returns false if the player is not on stairs down.
+    pub fn descend(&mut self, rng: &mut Rng) -> bool {
+        if self.map.at(self.player_pos()) != Tile::stairs_down() {
+            log::system("There are no stairs down here.");
+            return false;
+        }
+
+        let depth = self.depth + 1;
+        self.change_level(depth, Tile::stairs_up(), rng);
+        log::system(&format!("You descend to depth {}.", depth));
+        true
+    }
+
+    /// Takes the stairs up from where the player is standing.
+    ///
+    /// Returns false if the player is not on stairs up.
+    pub fn ascend(&mut self, rng: &mut Rng) -> bool {
+        if self.depth <= 1 || self.map.at(self.player_pos()) != Tile::stairs_up() {
+            log::system("There are no stairs up here.");
+            return false;
+        }
+
+        let depth = self.depth - 1;
+        self.change_level(depth, Tile::stairs_down(), rng);
+        log::system(&format!("You climb up to depth {}.", depth));
+        true
+    }
+
+    /// Puts the current level away and moves the player to another, arriving
+    /// on the staircase `arrival` leading back where they came from.
+    fn change_level(&mut self, depth: u32, arrival: Tile, rng: &mut Rng) {
+        let index = (depth - 1) as usize;
+        let current = (self.depth - 1) as usize;
+
+        while self.levels.len() <= cmp::max(index, current) {
+            self.levels.push(None);
+        }
+
+        let (map, entities, is_new) = match self.levels[index].take() {
+            Some(level) => (level.map, level.entities, false),
+            None => (level::generate_map(depth, rng), Ecs::new(), true),
+        };
+
+        let player = self.player();
+        let player = self.entities.take(player);
+
+        let previous = Level {
+            map: mem::replace(&mut self.map, map),
+            entities: mem::replace(&mut self.entities, entities),
+        };
+        self.levels[current] = Some(previous);
+        self.depth = depth;
+
+        let stairs = self.map.find_tile(arrival).unwrap_or(self.map.starting_position);
+        self.map.starting_position = stairs;
+        self.move_entity_aside(stairs);
+        self.entities.spawn(player.with_position(stairs));
+        self.update_fov();
+
+        if is_new {
+            self.spawn_monsters(MONSTER_COUNT, rng);
+            self.spawn_items(ITEM_COUNT, rng);
+        }
+    }
+
+    /// Moves any entity standing on a point to a free neighbouring tile, to
+    /// make room for the player
+    fn move_entity_aside(&mut self, loc: Point) {
+        if let Some(entity) = self.entities.entity_at(loc) {
+            let free = Direction::all().iter()
+                                       .map(|&direction| loc.move_dir(direction))
+                                       .find(|&next| self.is_free(next));
+
+            if let Some(next) = free {
+                self.entities.positions.insert(entity, Position(next));
+            }
         }
     }
 
-    pub fn walk(&mut self, direction: Direction) {
-        if !self.map.is_walkable(self.player.pos().move_dir(direction)) { return; }
+    /// Picks up the topmost item where the player is standing.
+    ///
+    /// Returns false if there was nothing to pick up or no room to carry it.
+    pub fn pick_up(&mut self) -> bool {
+        let player = self.player();
+        let pos = self.player_pos();
 
-        self.player.walk(direction);
+        let index = match self.map.items_at(pos).len() {
+            0 => {
+                log::loot("There is nothing here to pick up.");
+                return false;
+            }
+            count => count - 1,
+        };
+
+        let inventory = match self.entities.inventories.get_mut(player) {
+            Some(inventory) if !inventory.is_full() => inventory,
+            _ => {
+                log::loot("You cannot carry any more.");
+                return false;
+            }
+        };
+
+        let item = self.map.take_item(pos, index).expect("The item was just found");
+        log::loot(&format!("You pick up the {}.", log::coloured(item.name(), item.color())));
+        inventory.add(item).expect("The inventory has room");
+        true
     }
+
+    /// Drops an item from the player's inventory where they are standing.
+    ///
+    /// Returns false if there is no such item.
+    pub fn drop_item(&mut self, index: usize) -> bool {
+        let player = self.player();
+
+        match self.entities.inventories.get_mut(player).and_then(|i| i.remove(index)) {
+            Some(item) => {
+                log::loot(&format!("You drop the {}.", log::coloured(item.name(), item.color())));
+                let pos = self.player_pos();
+                self.map.place_item(pos, item);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Uses an item from the player's inventory.
+    ///
+    /// Returns false if there is no such item or it could not be used.
+    pub fn use_item(&mut self, index: usize) -> bool {
+        let player = self.player();
+
+        let (name, kind) = match self.entities.items(player).get(index) {
+            Some(item) => (item.name().to_string(), item.kind()),
+            None => return false,
+        };
+
+        match kind {
+            ItemKind::Potion { heal } => {
+                let missing = self.entities.max_health(player) - self.entities.health(player);
+                if missing <= 0 {
+                    log::loot("You are already at full health.");
+                    return false;
+                }
+
+                let amount = cmp::min(heal, missing);
+                self.entities.change_health(player, amount);
+                self.remove_item(player, index);
+                log::loot(&format!("You drink the {} and recover {{green:{}}} health.", log::escape(&name), amount));
+                true
+            }
+            ItemKind::Elixir { status } => {
+                self.remove_item(player, index);
+                log::loot(&format!("You drink the {}.", log::escape(&name)));
+                self.inflict(player, status);
+                true
+            }
+            ItemKind::Treasure => {
+                log::loot(&format!("You admire the {}.", log::escape(&name)));
+                false
+            }
+            ItemKind::Equipment { .. } => {
+                let item = self.remove_item(player, index).expect("The item was just found");
+                let previous = self.entities.equip(player, item).expect("Equipment can always be worn");
+                log::loot(&format!("You put on the {}.", log::escape(&name)));
+
+                // The new item's place in the inventory is free for whatever was worn before
+                if let Some(previous) = previous {
+                    log::loot(&format!("You take off the {}.", log::escape(previous.name())));
+                    self.add_item(player, previous).expect("The inventory has room");
+                }
+
+                true
+            }
+        }
+    }
+
+    /// Takes off whatever the player is wearing in a slot, putting it in
+    /// their inventory.
+    ///
+    /// Returns false if nothing is worn there or there is no room to carry it.
+    pub fn unequip(&mut self, slot: Slot) -> bool {
+        let player = self.player();
+
+        let name = match self.entities.equipped(player, slot) {
+            Some(item) => item.name().to_string(),
+            None => return false,
+        };
+
+        if self.entities.inventories.get(player).map_or(true, |i| i.is_full()) {
+            log::loot(&format!("You have no room to carry the {}.", log::escape(&name)));
+            return false;
+        }
+
+        let item = self.entities.unequip(player, slot).expect("The item was just found");
+        self.add_item(player, item).expect("The inventory has room");
+        log::loot(&format!("You take off the {}.", log::escape(&name)));
+        true
+    }
+
+    /// Adds an item to an entity's inventory, handing it back if there is
+    /// no room or the entity cannot carry anything
+    fn add_item(&mut self, entity: Entity, item: Item) -> Result<(), Item> {
+        match self.entities.inventories.get_mut(entity) {
+            Some(inventory) => inventory.add(item),
+            None => Err(item),
+        }
+    }
+
+    /// Removes an item from an entity's inventory, returning it if it was there
+    fn remove_item(&mut self, entity: Entity, index: usize) -> Option<Item> {
+        self.entities.inventories.get_mut(entity).and_then(|i| i.remove(index))
+    }
+
+    /// One entity attacks another. Anything killed is left for
+    /// `systems::remove_dead` to clear away.
+    fn attack(&mut self, attacker: Entity, defender: Entity) {
+        let damage = combat::melee(&mut self.entities, attacker, defender);
+
+        if self.entities.controlled.contains(attacker) {
+            let name = log::escape(self.entities.name(defender));
+
+            if damage > 0 {
+                log::combat(&format!("You hit the {} for {} damage.", name, damage));
+            } else {
+                log::combat(&format!("You hit the {} but do no damage.", name));
+            }
+        } else {
+            let name = log::escape(self.entities.name(attacker));
+
+            if damage > 0 {
+                log::combat(&format!("The {} hits you for {{red:{}}} damage.", name, damage));
+            } else {
+                log::combat(&format!("The {} hits you but does no damage.", name));
+            }
+
+            if self.entities.is_dead(defender) {
+                log::combat("{red:You die...}");
+            }
+        }
+
+        if damage > 0 && !self.entities.is_dead(defender) {
+            if let Some(on_hit) = self.entities.on_hits.get(attacker).cloned() {
+                self.inflict(defender, on_hit.0);
+            }
+        }
+    }
+
+    /// Puts an entity under a status effect, telling the player if it is them
+    pub fn inflict(&mut self, entity: Entity, status: Status) {
+        let added = self.entities.add_status(entity, status);
+
+        if !self.entities.controlled.contains(entity) {
+            return;
+        }
+
+        match added {
+            Added::New => log::combat(&log::coloured(status.kind.start_message(), status.kind.icon().1)),
+            Added::Stacked if status.kind.stacking() == Stacking::Intensify => {
+                log::combat(&format!("Your {} grows stronger.", status.kind.name().to_lowercase()));
+            }
+            Added::Stacked => log::combat(&format!("Your {} lasts longer.", status.kind.name().to_lowercase())),
+            Added::Unchanged => {}
+        }
+    }
+
+    /// Recalculates what the player can see from their current position
+    pub fn update_fov(&mut self) {
+        let pos = self.player_pos();
+        self.map.compute_fov(pos, FOV_RADIUS);
+    }
+
+    /// Returns the entity standing at a point, if there is one
+    pub fn entity_at(&self, loc: Point) -> Option<Entity> {
+        self.entities.entity_at(loc)
+    }
+
+    /// Returns true if the point can be walked on and nobody is standing there
+    pub fn is_free(&self, loc: Point) -> bool {
+        self.map.in_bounds(loc) && self.map.is_walkable(loc) && self.entity_at(loc).is_none()
+    }
+
+    /// Places up to `count` random monsters on free tiles away from the player
+    pub fn spawn_monsters(&mut self, count: usize, rng: &mut Rng) {
+        let mut free = vec![];
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                let loc = Point::new(x, y);
+                if self.is_free(loc) && !self.map.is_visible(loc) {
+                    free.push(loc);
+                }
+            }
+        }
+
+        for _ in 0..count {
+            if free.is_empty() {
+                break;
+            }
+
+            let index = rng.range(0, free.len() as i32) as usize;
+            let loc = free.swap_remove(index);
+            self.entities.spawn(monsters::random_monster(loc, rng));
+        }
+    }
+
+    /// Scatters `count` random items over walkable tiles
+    pub fn spawn_items(&mut self, count: usize, rng: &mut Rng) {
+        let player = self.player_pos();
+        let mut walkable = vec![];
+
+        for y in 0..self.map.height() {
+            for x in 0..self.map.width() {
+                let loc = Point::new(x, y);
+                if self.map.is_walkable(loc) && player != loc {
+                    walkable.push(loc);
+                }
+            }
+        }
+
+        for _ in 0..count {
+            if walkable.is_empty() {
+                break;
+            }
+
+            let index = rng.range(0, walkable.len() as i32) as usize;
+            let loc = walkable.swap_remove(index);
+            let item = loot::random_item(rng);
+            self.map.place_item(loc, item);
+        }
+    }
+}
+
+/// The player as they start a new game
+fn player(pos: Point) -> EntityBuilder {
+    EntityBuilder::new()
+        .with_name("Player")
+        .with_position(pos)
+        .with_health(Health::new(100))
+        .with_glyph('@', Color::new(255, 255, 255))
+        .with_stats(5, 1)
+        .with_energy(Energy::ready(NORMAL_SPEED))
+        .with_inventory(Inventory::new(PLAYER_CAPACITY))
+        .with_equipment(Equipment::new())
+        .controlled()
 }
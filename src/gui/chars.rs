@@ -1 +0,0 @@
-pub use tcod::chars::*;
@@ -1,4 +1,16 @@
-use tcod;
+pub use util::units::Color;
 
-pub use tcod::colors as Colors;
-pub type Color = tcod::Color;
+/// The named colours the interface is drawn with, the same as libtcod's
+pub struct Colors;
+
+impl Colors {
+    pub const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+    pub const DARKEST_GREY: Color = Color { r: 31, g: 31, b: 31 };
+    pub const DARK_GREY: Color = Color { r: 95, g: 95, b: 95 };
+    pub const GREY: Color = Color { r: 127, g: 127, b: 127 };
+    pub const LIGHT_GREY: Color = Color { r: 159, g: 159, b: 159 };
+    pub const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+    pub const RED: Color = Color { r: 255, g: 0, b: 0 };
+    pub const DARK_RED: Color = Color { r: 191, g: 0, b: 0 };
+    pub const DARK_GREEN: Color = Color { r: 0, g: 191, b: 0 };
+}
@@ -1,110 +1,253 @@
-extern crate tcod_sys;
-
-use gui::{Color, Colors};
-use util::units::{AsTuple, Point, Size};
-
-use tcod;
-use tcod::Console as TCODConsole;
-use tcod::input::Key as TKey;
-use tcod::input::KeyCode as TKeyCode;
-
-use std::path::Path;
+use gui::Color;
+use util::units::{Point, Size};
 
+/// A key on the keyboard, ignoring any modifiers held with it
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Key {
+pub enum KeyCode {
     Up,
     Down,
     Left,
     Right,
     Escape,
     Enter,
-    Char(char)
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// A function key, from 1 to 12
+    F(u8),
+    /// A digit on the numeric keypad
+    NumPad(u8),
+    Char(char),
 }
 
-pub struct Console {
-    console: tcod::RootConsole,
-    size: Size,
+/// The modifier keys held down during a keypress
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
 }
 
-impl Console {
-    pub fn new(size: Size) -> Console {
-        tcod::system::set_fps(60);
-
-        let (width, height) = size.as_tuple();
-        let console = tcod::RootConsole::initializer()
-                        .size(width, height)
-                        .title("Verbonia")
-                        .font(Path::new("assets/fonts/font.png"), tcod::FontLayout::AsciiInRow)
-                        .font_type(tcod::FontType::Greyscale)
-                        .font_dimensions(32, 64)
-                        .init();
-
-        unsafe {
-            // tcod_sys::TCOD_console_map_ascii_code_to_font(64, 1, 1);
-        }
+impl KeyModifiers {
+    pub fn none() -> KeyModifiers {
+        KeyModifiers::default()
+    }
 
-        Console {
-            console: console,
-            size: size,
-        }
+    pub fn is_empty(&self) -> bool {
+        !(self.shift || self.ctrl || self.alt)
     }
+}
 
-    pub fn put_plain(&mut self, pos: Point, c: char) {
-        self.console.put_char(pos.x, pos.y, c, tcod::BackgroundFlag::None);
+/// A keypress: a key along with the modifiers held while pressing it.
+///
+/// Shift is never set for `KeyCode::Char`, since the character itself
+/// already shows whether Shift was held (`>` rather than `.`, say).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+/// The names used for keys which are not written as a single character
+static KEY_NAMES: [(&'static str, KeyCode); 15] = [
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Escape", KeyCode::Escape),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Space", KeyCode::Char(' ')),
+];
+
+impl Key {
+    /// A keypress with no modifiers held
+    pub fn new(code: KeyCode) -> Key {
+        Key { code: code, modifiers: KeyModifiers::none() }
     }
 
-    pub fn put(&mut self, pos: Point, c: char, f_color: Color, b_color: Color) {
-        self.console.put_char_ex(pos.x, pos.y, c, f_color, b_color);
+    /// A keypress with modifiers. Shift is dropped for characters, as
+    /// described on `Key`.
+    pub fn with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        let mut modifiers = modifiers;
+        if let KeyCode::Char(_) = code {
+            modifiers.shift = false;
+        }
+
+        Key { code: code, modifiers: modifiers }
     }
 
-    pub fn print_plain(&mut self, pos: Point, text: &str) {
-        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::None, tcod::TextAlignment::Left, text);
+    pub fn char(c: char) -> Key {
+        Key::new(KeyCode::Char(c))
     }
 
-    pub fn print(&mut self, pos: Point, text: &str, f_color: Color, b_color: Color) {
-        self.console.set_default_background(b_color);
-        self.console.set_default_foreground(f_color);
-        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::Set, tcod::TextAlignment::Left, text);
-        self.console.set_default_background(Colors::Color::new(29, 29, 29));
-        self.console.set_default_foreground(Colors::WHITE);
+    pub fn shift(code: KeyCode) -> Key {
+        Key::with_modifiers(code, KeyModifiers { shift: true, ..KeyModifiers::none() })
     }
 
-    pub fn print_align(&mut self, pos: Point, text: &str, alignment: tcod::TextAlignment) {
-        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::None, alignment, text);
+    pub fn ctrl(code: KeyCode) -> Key {
+        Key::with_modifiers(code, KeyModifiers { ctrl: true, ..KeyModifiers::none() })
     }
 
-    pub fn clear(&mut self) {
-        self.console.clear();
+    pub fn alt(code: KeyCode) -> Key {
+        Key::with_modifiers(code, KeyModifiers { alt: true, ..KeyModifiers::none() })
     }
 
-    pub fn flush(&mut self) {
-        self.console.flush();
+    /// Looks up a key by the name `name` gives it, such as `k`, `NumPad8`,
+    /// `Shift+Up` or `Ctrl+s`
+    pub fn named(name: &str) -> Option<Key> {
+        let mut modifiers = KeyModifiers::none();
+        let mut rest = name;
+
+        loop {
+            // A lone "+" is the plus key rather than a modifier
+            if rest.len() > "Ctrl+".len() && rest.starts_with("Ctrl+") {
+                modifiers.ctrl = true;
+                rest = &rest["Ctrl+".len()..];
+            } else if rest.len() > "Alt+".len() && rest.starts_with("Alt+") {
+                modifiers.alt = true;
+                rest = &rest["Alt+".len()..];
+            } else if rest.len() > "Shift+".len() && rest.starts_with("Shift+") {
+                modifiers.shift = true;
+                rest = &rest["Shift+".len()..];
+            } else {
+                break;
+            }
+        }
+
+        KeyCode::named(rest).map(|code| Key::with_modifiers(code, modifiers))
     }
 
-    pub fn check_for_keypress(&mut self) -> Option<Key> {
-        let check_keypress = self.console.check_for_keypress(tcod::input::KEY_PRESSED);
-
-        if let Some(keypress) = check_keypress {
-            match keypress {
-                TKey { code: TKeyCode::Up, .. } => Some(Key::Up),
-                TKey { code: TKeyCode::Down, .. } => Some(Key::Down),
-                TKey { code: TKeyCode::Left, .. } => Some(Key::Left),
-                TKey { code: TKeyCode::Right, .. } => Some(Key::Right),
-                TKey { code: TKeyCode::Escape, .. } => Some(Key::Escape),
-                TKey { code: TKeyCode::Enter, .. } => Some(Key::Enter),
-                TKey { code: TKeyCode::Char, .. } => Some(Key::Char(keypress.printable)),
-                _ => None
+    /// A short name for the key, as used in key binding files
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+
+        if self.modifiers.ctrl { name.push_str("Ctrl+"); }
+        if self.modifiers.alt { name.push_str("Alt+"); }
+        if self.modifiers.shift { name.push_str("Shift+"); }
+
+        name.push_str(&self.code.name());
+        name
+    }
+}
+
+impl KeyCode {
+    fn named(name: &str) -> Option<KeyCode> {
+        let mut chars = name.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => return Some(KeyCode::Char(c)),
+            (None, _) => return None,
+            _ => {}
+        }
+
+        if let Some(&(_, code)) = KEY_NAMES.iter().find(|&&(n, _)| n == name) {
+            return Some(code);
+        }
+
+        if name.starts_with("NumPad") {
+            match name["NumPad".len()..].parse() {
+                Ok(digit) if digit <= 9 => Some(KeyCode::NumPad(digit)),
+                _ => None,
+            }
+        } else if name.starts_with('F') {
+            match name[1..].parse() {
+                Ok(n) if n >= 1 && n <= 12 => Some(KeyCode::F(n)),
+                _ => None,
             }
         } else {
             None
         }
     }
 
-    pub fn size(&self) -> Size {
-        self.size
+    fn name(&self) -> String {
+        match *self {
+            KeyCode::NumPad(digit) => format!("NumPad{}", digit),
+            KeyCode::F(n) => format!("F{}", n),
+            code => match KEY_NAMES.iter().find(|&&(_, c)| c == code) {
+                Some(&(name, _)) => name.to_string(),
+                None => match code {
+                    KeyCode::Char(c) => c.to_string(),
+                    _ => format!("{:?}", code),
+                },
+            },
+        }
     }
+}
+
+/// A button on the mouse
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+/// Something done with the mouse, at the cell of the console it happened over
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseEvent {
+    /// The mouse moved onto a different cell
+    Moved(Point),
+    /// A button was pressed
+    Pressed(MouseButton, Point),
+}
 
-    pub fn window_closed(&self) -> bool {
-        self.console.window_closed()
+impl MouseEvent {
+    /// The cell the event happened over
+    pub fn pos(&self) -> Point {
+        match *self {
+            MouseEvent::Moved(pos) | MouseEvent::Pressed(_, pos) => pos,
+        }
     }
 }
+
+/// How text is positioned relative to the point it is printed at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+/// Somewhere screens can be drawn, and keypresses read from.
+pub trait Console {
+    /// Draws a character, keeping the existing background
+    fn put_plain(&mut self, pos: Point, c: char);
+
+    fn put(&mut self, pos: Point, c: char, f_color: Color, b_color: Color);
+
+    /// Prints text, keeping the existing background
+    fn print_plain(&mut self, pos: Point, text: &str);
+
+    fn print(&mut self, pos: Point, text: &str, f_color: Color, b_color: Color);
+
+    fn print_align(&mut self, pos: Point, text: &str, alignment: Alignment);
+
+    fn clear(&mut self);
+
+    /// Shows everything drawn since the last flush
+    fn flush(&mut self);
+
+    /// Returns the next key pressed, if there is one, without waiting
+    fn check_for_keypress(&mut self) -> Option<Key>;
+
+    /// Returns the next thing done with the mouse, if anything, without
+    /// waiting
+    fn check_for_mouse(&mut self) -> Option<MouseEvent>;
+
+    fn size(&self) -> Size;
+
+    fn window_closed(&self) -> bool;
+}
@@ -10,18 +10,23 @@ pub enum State {
 }
 
 pub struct GUI {
-    pub console: Console,
+    pub console: Box<Console>,
     pub screens: Vec<Box<Screen>>,
     game: Game,
     state: State,
 }
 
 impl GUI {
-    pub fn new(game: Game, console: Console) -> GUI {
+    pub fn new(game: Game, console: Box<Console>) -> GUI {
+        GUI::with_screen(game, console, screens::MenuScreen::new())
+    }
+
+    /// Creates a GUI which starts on a screen other than the main menu
+    pub fn with_screen(game: Game, console: Box<Console>, screen: Box<Screen>) -> GUI {
         GUI {
             game: game,
             console: console,
-            screens: vec![screens::MenuScreen::new()],
+            screens: vec![screen],
             state: State::Running,
         }
     }
@@ -41,14 +46,19 @@ impl GUI {
     fn handle_input(&mut self) {
         let outcome = self.screens.first_mut()
                                   .expect("No screen to display")
-                                  .input(&mut self.game, &mut self.console);
+                                  .input(&mut self.game, &mut *self.console);
+
+        // Most screens ignore the mouse, so throw away whatever they left
+        // rather than letting it pile up
+        while self.console.check_for_mouse().is_some() {}
+
         self.update_screens(outcome);
     }
 
     fn update(&mut self) {
         let outcome = self.screens.first_mut()
                                   .expect("No screen to display")
-                                  .update(&mut self.game, &mut self.console);
+                                  .update(&mut self.game, &mut *self.console);
         self.update_screens(outcome);
     }
 
@@ -56,7 +66,7 @@ impl GUI {
         self.console.clear();
         self.screens.first_mut()
                     .expect("No screen to display")
-                    .render(&mut self.game, &mut self.console);
+                    .render(&mut self.game, &mut *self.console);
         self.console.flush();
     }
 
@@ -0,0 +1,176 @@
+use gui::{Alignment, Color, Colors, Console, Key, MouseEvent};
+use util::units::{Point, Size};
+
+use std::collections::VecDeque;
+
+/// A single character cell of a `HeadlessConsole`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub f_color: Color,
+    pub b_color: Color,
+}
+
+impl Cell {
+    fn blank() -> Cell {
+        Cell { c: ' ', f_color: Colors::WHITE, b_color: Colors::BLACK }
+    }
+}
+
+/// A console which draws into memory instead of a window.
+///
+/// Everything drawn is recorded per cell so that it can be inspected, and
+/// keypresses are read from a queue filled by `press`. This allows screens
+/// to be rendered and driven without a display. Flushing does nothing, so
+/// the cells always hold whatever was drawn since the last clear.
+pub struct HeadlessConsole {
+    cells: Vec<Cell>,
+    keys: VecDeque<Key>,
+    mouse: VecDeque<MouseEvent>,
+    size: Size,
+    closed: bool,
+}
+
+impl HeadlessConsole {
+    pub fn new(size: Size) -> HeadlessConsole {
+        HeadlessConsole {
+            cells: vec![Cell::blank(); (size.x * size.y) as usize],
+            keys: VecDeque::new(),
+            mouse: VecDeque::new(),
+            size: size,
+            closed: false,
+        }
+    }
+
+    /// Queues a keypress to be returned by `check_for_keypress`
+    pub fn press(&mut self, key: Key) {
+        self.keys.push_back(key);
+    }
+
+    /// Queues a mouse event to be returned by `check_for_mouse`
+    pub fn mouse(&mut self, event: MouseEvent) {
+        self.mouse.push_back(event);
+    }
+
+    /// Makes `window_closed` return true, as if the user closed the window
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /// Returns the cell at a position, or `None` if it is outside the console
+    pub fn cell(&self, pos: Point) -> Option<Cell> {
+        self.index(pos).map(|i| self.cells[i])
+    }
+
+    /// Returns one row of characters
+    pub fn row(&self, y: i32) -> String {
+        (0..self.size.x).filter_map(|x| self.cell(Point::new(x, y)))
+                        .map(|cell| cell.c)
+                        .collect()
+    }
+
+    /// Returns every row of characters, separated by newlines
+    pub fn text(&self) -> String {
+        (0..self.size.y).map(|y| self.row(y)).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Returns the position of the first occurrence of some text
+    pub fn find(&self, text: &str) -> Option<Point> {
+        (0..self.size.y).filter_map(|y| {
+            let row: Vec<char> = self.row(y).chars().collect();
+            let needle: Vec<char> = text.chars().collect();
+
+            if needle.is_empty() || needle.len() > row.len() {
+                return None;
+            }
+
+            (0..row.len() - needle.len() + 1).find(|&x| row[x..x + needle.len()] == needle[..])
+                                             .map(|x| Point::new(x as i32, y))
+        }).next()
+    }
+
+    fn index(&self, pos: Point) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x || pos.y >= self.size.y {
+            None
+        } else {
+            Some((pos.y * self.size.x + pos.x) as usize)
+        }
+    }
+
+    /// Updates a cell, ignoring positions outside the console like libtcod
+    fn set(&mut self, pos: Point, c: char, f_color: Color, b_color: Option<Color>) {
+        if let Some(i) = self.index(pos) {
+            let cell = &mut self.cells[i];
+            cell.c = c;
+            cell.f_color = f_color;
+
+            if let Some(b_color) = b_color {
+                cell.b_color = b_color;
+            }
+        }
+    }
+
+    /// Writes text starting at a position, continuing at the same column on
+    /// the next row after each newline
+    fn write(&mut self, pos: Point, text: &str, alignment: Alignment, f_color: Color, b_color: Option<Color>) {
+        for (row, line) in text.split('\n').enumerate() {
+            let width = line.chars().count() as i32;
+
+            let start_x = match alignment {
+                Alignment::Left => pos.x,
+                Alignment::Right => pos.x - width + 1,
+                Alignment::Center => pos.x - width / 2,
+            };
+
+            for (column, c) in line.chars().enumerate() {
+                self.set(Point::new(start_x + column as i32, pos.y + row as i32), c, f_color, b_color);
+            }
+        }
+    }
+}
+
+impl Console for HeadlessConsole {
+    fn put_plain(&mut self, pos: Point, c: char) {
+        self.set(pos, c, Colors::WHITE, None);
+    }
+
+    fn put(&mut self, pos: Point, c: char, f_color: Color, b_color: Color) {
+        self.set(pos, c, f_color, Some(b_color));
+    }
+
+    fn print_plain(&mut self, pos: Point, text: &str) {
+        self.write(pos, text, Alignment::Left, Colors::WHITE, None);
+    }
+
+    fn print(&mut self, pos: Point, text: &str, f_color: Color, b_color: Color) {
+        self.write(pos, text, Alignment::Left, f_color, Some(b_color));
+    }
+
+    fn print_align(&mut self, pos: Point, text: &str, alignment: Alignment) {
+        self.write(pos, text, alignment, Colors::WHITE, None);
+    }
+
+    fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::blank();
+        }
+    }
+
+    fn flush(&mut self) {}
+
+    fn check_for_keypress(&mut self) -> Option<Key> {
+        self.keys.pop_front()
+    }
+
+    fn check_for_mouse(&mut self) -> Option<MouseEvent> {
+        self.mouse.pop_front()
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn window_closed(&self) -> bool {
+        self.closed
+    }
+}
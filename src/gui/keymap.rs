@@ -0,0 +1,510 @@
+//! Key bindings, defined in a data file rather than in code
+//!
+//! Screens never look at keys directly. Instead they ask the current
+//! `Keymap` which `Action` a key stands for in their `Context`. The default
+//! bindings are compiled from `assets/keys.txt`, and a player's own
+//! bindings can be loaded over them at startup with `load_file`.
+
+use engine::{Category, Slot};
+use gui::Key;
+use util::units::Direction;
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+thread_local!(static KEYMAP: RefCell<Keymap> = RefCell::new(Keymap::builtin()));
+
+/// The key bindings compiled into the game
+static BUILTIN_KEYS: &'static str = include_str!("../../assets/keys.txt");
+
+/// Where the player's own key bindings are kept
+pub static DEFAULT_PATH: &'static str = "keys.txt";
+
+/// Loads key bindings from a file over the defaults, replacing the current
+/// bindings.
+pub fn load_file<P>(path: P) -> Result<(), KeymapError> where P: AsRef<Path> {
+    let keymap = Keymap::from_file(path)?;
+    set(keymap);
+    Ok(())
+}
+
+/// Returns a copy of the current bindings
+pub fn current() -> Keymap {
+    KEYMAP.with(|k| k.borrow().clone())
+}
+
+/// Replaces the current bindings
+pub fn set(keymap: Keymap) {
+    KEYMAP.with(|k| *k.borrow_mut() = keymap);
+}
+
+/// Returns the action a key is bound to in a context, if any
+pub fn action(context: Context, key: Key) -> Option<Action> {
+    KEYMAP.with(|k| k.borrow().action(context, key))
+}
+
+/// Describes the first key bound to an action, for showing in help text
+pub fn key_name(context: Context, action: Action) -> String {
+    KEYMAP.with(|k| {
+        k.borrow().keys(context, action).first().map_or("(none)".to_string(), |key| key.name())
+    })
+}
+
+/// A set of screens which share their key bindings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Context {
+    /// The main menu, the pause menu and other lists of options
+    Menu,
+    Game,
+    Inventory,
+    Replay,
+    /// The message history
+    History,
+}
+
+static CONTEXTS: [Context; 5] = [Context::Menu, Context::Game, Context::Inventory, Context::Replay, Context::History];
+
+/// Actions which must always have a key, since without them the player
+/// could not get around the menus or back to the Key Bindings screen
+static REQUIRED: [(Context, Action); 5] = [
+    (Context::Menu, Action::Up),
+    (Context::Menu, Action::Down),
+    (Context::Menu, Action::Select),
+    (Context::Menu, Action::Back),
+    (Context::Game, Action::Pause),
+];
+
+static MENU_ACTIONS: [(&'static str, Action); 4] = [
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("select", Action::Select),
+    ("back", Action::Back),
+];
+
+static GAME_ACTIONS: [(&'static str, Action); 28] = [
+    ("walk_up", Action::Walk(Direction::Up)),
+    ("walk_down", Action::Walk(Direction::Down)),
+    ("walk_left", Action::Walk(Direction::Left)),
+    ("walk_right", Action::Walk(Direction::Right)),
+    ("walk_up_left", Action::Walk(Direction::UpLeft)),
+    ("walk_up_right", Action::Walk(Direction::UpRight)),
+    ("walk_down_left", Action::Walk(Direction::DownLeft)),
+    ("walk_down_right", Action::Walk(Direction::DownRight)),
+    ("wait", Action::Walk(Direction::Wait)),
+    ("run_up", Action::Run(Direction::Up)),
+    ("run_down", Action::Run(Direction::Down)),
+    ("run_left", Action::Run(Direction::Left)),
+    ("run_right", Action::Run(Direction::Right)),
+    ("run_up_left", Action::Run(Direction::UpLeft)),
+    ("run_up_right", Action::Run(Direction::UpRight)),
+    ("run_down_left", Action::Run(Direction::DownLeft)),
+    ("run_down_right", Action::Run(Direction::DownRight)),
+    ("pick_up", Action::PickUp),
+    ("descend", Action::Descend),
+    ("ascend", Action::Ascend),
+    ("inventory", Action::Inventory),
+    ("message_history", Action::MessageHistory),
+    ("save", Action::Save),
+    ("pause", Action::Pause),
+    ("toggle_combat_messages", Action::ToggleMessages(Category::Combat)),
+    ("toggle_loot_messages", Action::ToggleMessages(Category::Loot)),
+    ("toggle_system_messages", Action::ToggleMessages(Category::System)),
+    ("toggle_debug_messages", Action::ToggleMessages(Category::Debug)),
+];
+
+static INVENTORY_ACTIONS: [(&'static str, Action); 8] = [
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("use", Action::Use),
+    ("drop", Action::Drop),
+    ("take_off_weapon", Action::TakeOff(Slot::Weapon)),
+    ("take_off_armour", Action::TakeOff(Slot::Armour)),
+    ("take_off_ring", Action::TakeOff(Slot::Ring)),
+    ("close", Action::Back),
+];
+
+static REPLAY_ACTIONS: [(&'static str, Action); 5] = [
+    ("pause", Action::TogglePause),
+    ("faster", Action::Faster),
+    ("slower", Action::Slower),
+    ("step", Action::Step),
+    ("quit", Action::Back),
+];
+
+static HISTORY_ACTIONS: [(&'static str, Action); 11] = [
+    ("up", Action::Up),
+    ("down", Action::Down),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("oldest", Action::Top),
+    ("newest", Action::Bottom),
+    ("search", Action::Search),
+    ("next_match", Action::NextMatch),
+    ("previous_match", Action::PreviousMatch),
+    ("toggle_filter", Action::ToggleFilter),
+    ("close", Action::Back),
+];
+
+impl Context {
+    pub fn all() -> &'static [Context; 5] {
+        &CONTEXTS
+    }
+
+    /// The name of the context's section in key binding files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Context::Menu => "menu",
+            Context::Game => "game",
+            Context::Inventory => "inventory",
+            Context::Replay => "replay",
+            Context::History => "history",
+        }
+    }
+
+    /// Every action which can be bound in the context, with its name
+    pub fn actions(&self) -> &'static [(&'static str, Action)] {
+        match *self {
+            Context::Menu => &MENU_ACTIONS,
+            Context::Game => &GAME_ACTIONS,
+            Context::Inventory => &INVENTORY_ACTIONS,
+            Context::Replay => &REPLAY_ACTIONS,
+            Context::History => &HISTORY_ACTIONS,
+        }
+    }
+
+    fn named(name: &str) -> Option<Context> {
+        CONTEXTS.iter().find(|c| c.name() == name).cloned()
+    }
+
+    /// Whether an action must always have at least one key in the context
+    pub fn requires(&self, action: Action) -> bool {
+        REQUIRED.contains(&(*self, action))
+    }
+}
+
+/// Something the player can do by pressing a key
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Moves a selection up
+    Up,
+    /// Moves a selection down
+    Down,
+    Select,
+    /// Leaves the current screen
+    Back,
+    Walk(Direction),
+    /// Walks in a direction until something interesting happens
+    Run(Direction),
+    PickUp,
+    Descend,
+    Ascend,
+    /// Opens the inventory
+    Inventory,
+    /// Opens the message history
+    MessageHistory,
+    /// Saves the game without going through the pause menu
+    Save,
+    /// Opens the pause menu
+    Pause,
+    /// Shows or hides one category of message
+    ToggleMessages(Category),
+    /// Uses or puts on the selected item
+    Use,
+    Drop,
+    TakeOff(Slot),
+    /// Pauses or resumes a replay
+    TogglePause,
+    Faster,
+    Slower,
+    /// Plays back a single command
+    Step,
+    /// Scrolls up or down by a screenful
+    PageUp,
+    PageDown,
+    /// Scrolls to the very beginning or end
+    Top,
+    Bottom,
+    /// Starts typing something to look for
+    Search,
+    /// Jumps to the next match further back in the message history
+    NextMatch,
+    /// Jumps to the next match further forward in the message history
+    PreviousMatch,
+    /// Switches between every message and only those the log shows
+    ToggleFilter,
+}
+
+/// The keys bound to one action in one context
+#[derive(Clone, Debug)]
+struct Binding {
+    context: Context,
+    action: Action,
+    keys: Vec<Key>,
+}
+
+/// Every key binding, for every context
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// The key bindings compiled into the game
+    pub fn builtin() -> Keymap {
+        let mut keymap = Keymap::unbound();
+        keymap.parse(BUILTIN_KEYS).expect("The built-in key bindings are invalid");
+        keymap
+    }
+
+    /// Loads bindings from a file. Any action the file leaves out keeps its
+    /// built-in bindings.
+    pub fn from_file<P>(path: P) -> Result<Keymap, KeymapError> where P: AsRef<Path> {
+        let mut file = File::open(path)?;
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        Keymap::from_string(&contents)
+    }
+
+    /// Parses key bindings over the built-in ones.
+    ///
+    /// Each context begins with its name in square brackets, followed by one
+    /// `action = keys` line per action. Blank lines and lines starting with
+    /// `#` are ignored. An action which must always have a key keeps its
+    /// built-in keys if the bindings leave it without any.
+    pub fn from_string(s: &str) -> Result<Keymap, KeymapError> {
+        let builtin = Keymap::builtin();
+        let mut keymap = builtin.clone();
+        keymap.parse(s)?;
+
+        for &(context, action) in REQUIRED.iter() {
+            if keymap.keys(context, action).is_empty() {
+                for &key in builtin.keys(context, action) {
+                    keymap.add_key(context, action, key);
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Writes every binding in the same format `from_string` reads
+    pub fn save<P>(&self, path: P) -> io::Result<()> where P: AsRef<Path> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_string().as_bytes())
+    }
+
+    /// Returns the action a key is bound to in a context, if any
+    pub fn action(&self, context: Context, key: Key) -> Option<Action> {
+        self.bindings.iter()
+                     .find(|b| b.context == context && b.keys.contains(&key))
+                     .map(|b| b.action)
+    }
+
+    /// Returns the keys bound to an action in a context
+    pub fn keys(&self, context: Context, action: Action) -> &[Key] {
+        self.bindings.iter()
+                     .find(|b| b.context == context && b.action == action)
+                     .map_or(&[], |b| &b.keys)
+    }
+
+    /// Adds a key to an action, taking it away from any other action in the
+    /// same context.
+    ///
+    /// Returns false without changing anything if that would leave an action
+    /// which must always have a key without one.
+    pub fn bind(&mut self, context: Context, action: Action, key: Key) -> bool {
+        let strands = self.bindings.iter().any(|b| {
+            b.context == context && b.action != action && context.requires(b.action) && b.keys == [key]
+        });
+
+        if strands {
+            return false;
+        }
+
+        self.add_key(context, action, key);
+        true
+    }
+
+    /// Removes every key from an action, unless it must always have a key.
+    /// Returns whether the keys were removed.
+    pub fn clear(&mut self, context: Context, action: Action) -> bool {
+        if context.requires(action) {
+            return false;
+        }
+
+        self.remove_keys(context, action);
+        true
+    }
+
+    /// A keymap with every action present but no keys bound
+    fn unbound() -> Keymap {
+        let mut bindings = vec![];
+
+        for &context in Context::all().iter() {
+            for &(_, action) in context.actions().iter() {
+                bindings.push(Binding { context: context, action: action, keys: vec![] });
+            }
+        }
+
+        Keymap { bindings: bindings }
+    }
+
+    /// Binds a key like `bind`, even if it leaves an action with no keys
+    fn add_key(&mut self, context: Context, action: Action, key: Key) {
+        for binding in self.bindings.iter_mut().filter(|b| b.context == context) {
+            if binding.action == action {
+                if !binding.keys.contains(&key) {
+                    binding.keys.push(key);
+                }
+            } else {
+                binding.keys.retain(|&k| k != key);
+            }
+        }
+    }
+
+    fn remove_keys(&mut self, context: Context, action: Action) {
+        if let Some(binding) = self.binding_mut(context, action) {
+            binding.keys.clear();
+        }
+    }
+
+    fn binding_mut(&mut self, context: Context, action: Action) -> Option<&mut Binding> {
+        self.bindings.iter_mut().find(|b| b.context == context && b.action == action)
+    }
+
+    fn parse(&mut self, s: &str) -> Result<(), KeymapError> {
+        let mut context = None;
+
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim();
+                match Context::named(name) {
+                    Some(c) => context = Some(c),
+                    None => return Err(syntax(line_number, &format!("unknown section `{}`", name))),
+                }
+                continue;
+            }
+
+            let context = match context {
+                Some(context) => context,
+                None => return Err(syntax(line_number, "binding given before any section name")),
+            };
+
+            let (name, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(syntax(line_number, "expected `action = keys`")),
+            };
+
+            let action = match context.actions().iter().find(|&&(n, _)| n == name) {
+                Some(&(_, action)) => action,
+                None => return Err(syntax(line_number, &format!("unknown action `{}` in [{}]", name, context.name()))),
+            };
+
+            let mut keys = vec![];
+            for key_name in value.split_whitespace() {
+                match Key::named(key_name) {
+                    Some(key) => keys.push(key),
+                    None => return Err(syntax(line_number, &format!("unknown key `{}`", key_name))),
+                }
+            }
+
+            self.remove_keys(context, action);
+            for key in keys {
+                self.add_key(context, action, key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, &context) in Context::all().iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "[{}]", context.name())?;
+
+            for &(name, action) in context.actions().iter() {
+                let keys: Vec<String> = self.keys(context, action).iter().map(|k| k.name()).collect();
+                writeln!(f, "{} = {}", name, keys.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn syntax(line: usize, reason: &str) -> KeymapError {
+    KeymapError::Syntax { line: line, reason: reason.to_string() }
+}
+
+/// An error encountered while loading key bindings
+#[derive(Debug)]
+pub enum KeymapError {
+    /// The bindings could not be read
+    Io(io::Error),
+    /// A line could not be understood
+    Syntax { line: usize, reason: String },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeymapError::Io(ref err) => write!(f, "could not read key bindings: {}", err),
+            KeymapError::Syntax { line, ref reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl Error for KeymapError {}
+
+impl From<io::Error> for KeymapError {
+    fn from(err: io::Error) -> KeymapError {
+        KeymapError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Context, Keymap};
+    use gui::{Key, KeyCode};
+
+    #[test]
+    fn keeps_a_key_on_required_actions() {
+        let mut keymap = Keymap::builtin();
+        let escape = Key::new(KeyCode::Escape);
+
+        assert!(!keymap.clear(Context::Game, Action::Pause));
+        assert!(!keymap.bind(Context::Game, Action::Save, escape));
+        assert_eq!(keymap.action(Context::Game, escape), Some(Action::Pause));
+
+        assert!(keymap.bind(Context::Game, Action::Pause, Key::new(KeyCode::Char('p'))));
+        assert!(keymap.bind(Context::Game, Action::Save, escape));
+        assert_eq!(keymap.keys(Context::Game, Action::Pause), &[Key::new(KeyCode::Char('p'))]);
+    }
+
+    #[test]
+    fn loading_restores_required_keys() {
+        let keymap = Keymap::from_string("[menu]\nselect =\n[game]\nsave = Escape\n").unwrap();
+
+        assert_eq!(keymap.keys(Context::Menu, Action::Select), &[Key::new(KeyCode::Enter)]);
+        assert_eq!(keymap.action(Context::Game, Key::new(KeyCode::Escape)), Some(Action::Pause));
+        assert!(keymap.keys(Context::Game, Action::Save).is_empty());
+    }
+}
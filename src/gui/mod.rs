@@ -1,17 +1,23 @@
 //! The roguelike's user interface
 
 pub use self::colors::{Color, Colors};
-pub use self::console::{Console, Key};
+pub use self::console::{Alignment, Console, Key, KeyCode, KeyModifiers, MouseButton, MouseEvent};
+pub use self::headless_console::{Cell, HeadlessConsole};
 pub use self::gui::GUI;
 pub use self::menu::{Menu, MenuOption};
+pub use self::tcod_console::TcodConsole;
+pub use self::terminal_console::TerminalConsole;
 pub use self::widget::Widget;
 
 pub mod screens;
-pub mod chars;
+pub mod keymap;
 pub mod primitives;
 
 mod colors;
 mod console;
 mod gui;
+mod headless_console;
 mod menu;
+mod tcod_console;
+mod terminal_console;
 mod widget;
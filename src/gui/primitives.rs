@@ -1,4 +1,3 @@
-use gui::chars;
 use gui::Console;
 use util::units::BorderedRectangle;
 use util::units::Point;
@@ -31,5 +30,5 @@ pub fn draw_box_with_title(console: &mut Console, title: &str, rect: BorderedRec
     console.put_plain(rect.location() + Point::new(title.len() as i32 + 5, 0), '\u{6}');
     console.put_plain(rect.location() + (3, 0), '\u{81}');
     console.put_plain(rect.location() + Point::new(title.len() as i32 + 4, 0), '\u{82}');
-    console.print(rect.location() + (4, 0), title, Colors::BLACK, Colors::Color::new(236, 229, 206));
+    console.print(rect.location() + (4, 0), title, Colors::BLACK, Color::new(236, 229, 206));
 }
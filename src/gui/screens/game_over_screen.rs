@@ -0,0 +1,49 @@
+use engine::Game;
+use gui::{Console, KeyCode};
+use gui::screens::{Screen, ScreenChange};
+use util::units::Point;
+
+/// Shown once the player has died
+pub struct GameOverScreen;
+
+impl GameOverScreen {
+    pub fn new() -> Box<Screen> {
+        Box::new(GameOverScreen)
+    }
+}
+
+impl Screen for GameOverScreen {
+    #[allow(unused)]
+    fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        if let Some(key) = console.check_for_keypress() {
+            match key.code {
+                KeyCode::Enter | KeyCode::Escape => return Some(ScreenChange::ExitGame),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        None
+    }
+
+    #[allow(unused)]
+    fn render(&mut self, game: &mut Game, console: &mut Console) {
+        let lines = [
+            "You have died.".to_string(),
+            format!("You survived for {} turns.", game.turn),
+            String::new(),
+            "Press Enter to exit".to_string(),
+        ];
+
+        let center = Point::new(console.size().x / 2, console.size().y / 2 - lines.len() as i32 / 2);
+
+        for (i, line) in lines.iter().enumerate() {
+            let pos = center.down(i as i32).left(line.len() as i32 / 2);
+            console.print_plain(pos, line);
+        }
+    }
+}
@@ -1,9 +1,17 @@
-use engine::{Game, Command, MessageType, Tile};
-use engine::log;
+use engine::{Game, Command, Slot, Tile};
+use engine::{log, save};
+use engine::path::{self, Costs};
+use engine::scheduler;
 use gui::{primitives};
-use gui::{Console, Colors, Key, Widget};
+use gui::{Color, Console, Colors, MouseButton, MouseEvent, Widget};
+use gui::keymap::{self, Action, Context};
 use gui::screens::{self, Screen, ScreenChange};
-use util::units::{AsTuple, Direction, Point, Size};
+use util::units::{AsTuple, Direction, Offset, Point, Size};
+
+use std::cmp;
+
+/// The furthest the player will run in one go
+const MAX_RUN_STEPS: u32 = 100;
 
 #[allow(missing_copy_implementations)]
 pub struct GameScreen {
@@ -11,6 +19,10 @@ pub struct GameScreen {
     info: Widget,
     messages: Widget,
     map_view: Point,
+    /// The point on the map under the mouse, if it is over the map
+    hover: Option<Point>,
+    /// Where the player is walking to after clicking on the map
+    travel: Option<Point>,
 }
 
 impl GameScreen {
@@ -25,6 +37,8 @@ impl GameScreen {
                 info: Widget::new(info_widget_location, Size::new(18, 48)),
                 messages: Widget::new(message_widget_location, Size::new(59, 13)),
                 map_view: Point::new(0, 0),
+                hover: None,
+                travel: None,
             }
         )
     }
@@ -33,23 +47,48 @@ impl GameScreen {
 impl Screen for GameScreen {
     #[allow(unused)]
     fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        while let Some(event) = console.check_for_mouse() {
+            let map_pos = self.map.local_position(event.pos()).map(|pos| pos + self.map_view);
+
+            match event {
+                MouseEvent::Moved(_) => self.hover = map_pos,
+                MouseEvent::Pressed(MouseButton::Left, _) => {
+                    if let Some(target) = map_pos {
+                        if game.world.map.is_explored(target) && travel_path(game, target).is_some() {
+                            self.travel = Some(target);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
         if let Some(key) = console.check_for_keypress() {
-            match key {
-                Key::Up => {
-                    game.do_command(Command::Walk(Direction::Up));
-                },
-                Key::Down => {
-                    game.do_command(Command::Walk(Direction::Down));
-                },
-                Key::Left => {
-                    game.do_command(Command::Walk(Direction::Left));
-                },
-                Key::Right => {
-                    game.do_command(Command::Walk(Direction::Right));
-                },
-                Key::Escape => {
-                    return Some(ScreenChange::AddScreen(screens::PauseScreen::new()));
-                },
+            // Any key stops the player walking to where they clicked
+            self.travel = None;
+
+            match keymap::action(Context::Game, key) {
+                Some(Action::Pause) => return Some(ScreenChange::AddScreen(screens::PauseScreen::new())),
+                Some(Action::Inventory) => return Some(ScreenChange::AddScreen(screens::InventoryScreen::new())),
+                Some(Action::MessageHistory) => {
+                    return Some(ScreenChange::AddScreen(screens::MessageHistoryScreen::new()));
+                }
+                Some(Action::Walk(direction)) => game.do_command(Command::Walk(direction)),
+                Some(Action::Run(direction)) => run(game, direction),
+                Some(Action::Save) => {
+                    match game.save(save::DEFAULT_PATH) {
+                        Ok(()) => log::system("Game saved."),
+                        Err(e) => log::error(&format!("Could not save the game: {}", e)),
+                    }
+                }
+                Some(Action::PickUp) => game.do_command(Command::PickUp),
+                Some(Action::Descend) => game.do_command(Command::Descend),
+                Some(Action::Ascend) => game.do_command(Command::Ascend),
+                Some(Action::ToggleMessages(category)) => {
+                    let shown = log::LOG.with(|l| l.borrow_mut().filter.toggle(category));
+                    let state = if shown { "Showing" } else { "Hiding" };
+                    log::notice(&format!("{} {} messages.", state, category.name()));
+                }
                 _ => {}
             }
         }
@@ -60,6 +99,17 @@ impl Screen for GameScreen {
     #[allow(unused)]
     fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
         game.step();
+
+        if let Some(target) = self.travel {
+            if !travel_step(game, target) {
+                self.travel = None;
+            }
+        }
+
+        if game.world.is_player_dead() {
+            return Some(ScreenChange::AddScreen(screens::GameOverScreen::new()));
+        }
+
         None
     }
 
@@ -68,6 +118,8 @@ impl Screen for GameScreen {
         self.draw_borders(game, console);
         self.draw_info(game, console);
         self.draw_map(game, console);
+        self.draw_items(game, console);
+        self.draw_entities(game, console);
         self.draw_player(game, console);
         self.draw_messages(game, console);
     }
@@ -93,7 +145,7 @@ impl GameScreen {
             }
         }
         console.put_plain(Point::new(width - 1, 0), '\u{8}');
-        let bg_color = Colors::Color::new(236, 229, 206);
+        let bg_color = Color::new(236, 229, 206);
         console.put_plain(Point::new(3, 0), '\u{8}');
         console.put(Point::new(4, 0), ' ', Colors::WHITE, bg_color);
         console.put(Point::new(5, 0), ' ', Colors::WHITE, bg_color);
@@ -112,7 +164,102 @@ impl GameScreen {
 
     #[allow(unused)]
     fn draw_info(&self, game: &mut Game, console: &mut Console) {
-        return;
+        let entities = &game.world.entities;
+        let player = game.world.player();
+        let bonus = entities.modifiers(player);
+
+        self.info.print(console, Point::new(0, 0), entities.name(player), Colors::WHITE, Colors::BLACK);
+        self.draw_health_bar(game, console, Point::new(0, 1));
+
+        let stats = [
+            ("Atk", entities.attack(player), bonus.attack),
+            ("Def", entities.defence(player), bonus.defence),
+        ];
+
+        for (i, &(label, value, bonus)) in stats.iter().enumerate() {
+            let line = if bonus != 0 {
+                format!("{:<8}{} ({:+})", label, value, bonus)
+            } else {
+                format!("{:<8}{}", label, value)
+            };
+
+            self.info.print_plain(console, Point::new(0, 3 + i as i32), &line);
+        }
+
+        let pos = game.world.player_pos();
+        self.info.print_plain(console, Point::new(0, 6), &format!("{:<8}{}", "Depth", game.world.depth));
+        self.info.print_plain(console, Point::new(0, 7), &format!("{:<8}{}", "Turn", game.turn));
+        self.info.print_plain(console, Point::new(0, 8), &format!("{:<8}{}", "Time", scheduler::format_time(game.time)));
+        self.info.print_plain(console, Point::new(0, 9), &format!("{:<8}{},{}", "Pos", pos.x, pos.y));
+
+        for (i, &slot) in Slot::all().iter().enumerate() {
+            let y = 11 + i as i32 * 2;
+            self.info.print(console, Point::new(0, y), slot.name(), Colors::GREY, Colors::BLACK);
+
+            match entities.equipped(player, slot) {
+                Some(item) => {
+                    self.info.put(console, Point::new(1, y + 1), item.glyph(), item.color(), Colors::BLACK);
+                    self.info.print_plain(console, Point::new(3, y + 1), item.name());
+                }
+                None => self.info.print_plain(console, Point::new(1, y + 1), "-"),
+            }
+        }
+
+        self.info.print(console, Point::new(0, 18), "Status", Colors::GREY, Colors::BLACK);
+
+        let statuses: Vec<_> = entities.statuses.get(player).iter().flat_map(|s| s.iter()).cloned().collect();
+
+        if statuses.is_empty() {
+            self.info.print_plain(console, Point::new(1, 19), "Normal");
+        }
+
+        for (i, status) in statuses.iter().enumerate() {
+            let y = 19 + i as i32;
+            let (icon, color) = status.kind.icon();
+            self.info.put(console, Point::new(1, y), icon, color, Colors::BLACK);
+            self.info.print_plain(console, Point::new(3, y), &status.label());
+        }
+
+        if let Some(pos) = self.hover {
+            let y = 21 + cmp::max(statuses.len() as i32 - 1, 0);
+            self.info.print(console, Point::new(0, y), "Looking at", Colors::GREY, Colors::BLACK);
+
+            for (i, line) in describe(game, pos).iter().enumerate() {
+                self.info.print_plain(console, Point::new(1, y + 1 + i as i32), line);
+            }
+        }
+    }
+
+    /// Draws the player's health as a bar across the info panel, shading
+    /// from green to red as it runs out
+    fn draw_health_bar(&self, game: &Game, console: &mut Console, pos: Point) {
+        let entities = &game.world.entities;
+        let player = game.world.player();
+        let width = self.info.inner_size().x;
+
+        let health = cmp::max(entities.health(player), 0);
+        let max_health = cmp::max(entities.max_health(player), 1);
+        let fraction = health as f32 / max_health as f32;
+
+        // Any health at all shows at least one filled cell
+        let filled = if health > 0 { cmp::max(1, width * health / max_health) } else { 0 };
+
+        let bar_color = Colors::DARK_RED.lerp(Colors::DARK_GREEN, fraction);
+        let empty_color = Color::new(40, 40, 40);
+
+        let text = format!("HP {}/{}", entities.health(player), entities.max_health(player));
+        let text_start = (width - text.len() as i32) / 2;
+
+        for x in 0..width {
+            let c = if x >= text_start {
+                text.chars().nth((x - text_start) as usize).unwrap_or(' ')
+            } else {
+                ' '
+            };
+
+            let b_color = if x < filled { bar_color } else { empty_color };
+            self.info.put(console, pos.right(x), c, Colors::WHITE, b_color);
+        }
     }
 
     #[allow(unused)]
@@ -124,21 +271,73 @@ impl GameScreen {
 
         for (y, line) in map.tiles[uy .. uy + height + 1].iter().enumerate() {
             for (x, cell) in line[ux .. ux + width + 1].iter().enumerate() {
-                let bg_color = match *cell {
-                    Tile::Empty => Colors::BLACK,
-                    Tile::Wall => Colors::DARKER_GREY,
-                    Tile::Floor => Colors::DARKEST_SEPIA,
-                    Tile::Grass => Colors::DESATURATED_GREEN,
+                let map_pos = self.map_view + (x as i32, y as i32);
+
+                let fg_color = cell.foreground();
+                let bg_color = cell.background();
+
+                // Remembered tiles are dimmed and unexplored tiles are hidden
+                let (glyph, fg_color, bg_color) = if map.is_visible(map_pos) {
+                    (cell.glyph(), fg_color, bg_color)
+                } else if map.is_explored(map_pos) {
+                    (cell.glyph(), fg_color.lerp(Colors::BLACK, 0.6), bg_color.lerp(Colors::BLACK, 0.6))
+                } else {
+                    (' ', Colors::WHITE, Colors::BLACK)
                 };
 
-                self.map.put(console, Point::new(x as i32, y as i32), ' ', Colors::WHITE, bg_color);
+                self.map.put(console, Point::new(x as i32, y as i32), glyph, fg_color, bg_color);
+            }
+        }
+    }
+
+    #[allow(unused)]
+    fn draw_items(&self, game: &mut Game, console: &mut Console) {
+        let (width, height): (i32, i32) = self.map.rect.inner_size().as_tuple();
+        let map = &game.world.map;
+
+        for view_y in 0..height + 1 {
+            for view_x in 0..width + 1 {
+                let view_pos = Point::new(view_x, view_y);
+                let pos = view_pos + self.map_view;
+
+                if !map.is_visible(pos) {
+                    continue;
+                }
+
+                if let Some(item) = map.items_at(pos).last() {
+                    self.map.put(console, view_pos, item.glyph(), item.color(), map.at(pos).background());
+                }
             }
         }
     }
 
+    /// Draws every visible entity other than the player, who is drawn last
+    /// so that the view can follow them
+    #[allow(unused)]
+    fn draw_entities(&self, game: &mut Game, console: &mut Console) {
+        let (width, height) = self.map.rect.inner_size().as_tuple();
+        let map = &game.world.map;
+        let entities = &game.world.entities;
+
+        for (&entity, renderable) in entities.renderables.iter() {
+            let pos = match entities.position(entity) {
+                Some(pos) if !entities.controlled.contains(entity) => pos,
+                _ => continue,
+            };
+            let view_pos = pos - self.map_view;
+
+            let in_view = view_pos.x >= 0 && view_pos.y >= 0 && view_pos.x <= width && view_pos.y <= height;
+            if !in_view || !map.is_visible(pos) {
+                continue;
+            }
+
+            self.map.put(console, view_pos, renderable.glyph, renderable.color, map.at(pos).background());
+        }
+    }
+
     #[allow(unused)]
     fn draw_player(&mut self, game: &mut Game, console: &mut Console) {
-        let pos = *game.world.player.pos();
+        let pos = game.world.player_pos();
 
         let adjusted_pos = pos + self.map.rect.inner_location() - self.map_view;
 
@@ -173,16 +372,23 @@ impl GameScreen {
 
     #[allow(unused)]
     fn draw_messages(&mut self, game: &mut Game, console: &mut Console) {
-        let nmessages = self.messages.rect.height() as usize;
+        let size = self.messages.inner_size();
+        let mut y = 0;
 
+        // The newest message goes at the top, with any lines it wraps onto
+        // beneath it
         log::LOG.with(|w| {
-            for (i, msg) in w.borrow().items().take(nmessages).enumerate() {
-                let message_color = match *msg.ty() {
-                    MessageType::Info => Colors::WHITE,
-                    MessageType::Error => Colors::RED,
-                };
+            for msg in w.borrow().visible() {
+                let color = msg.category().color();
 
-                self.messages.print(console, Point::new(0, i as i32), msg.text(), message_color, Colors::BLACK);
+                for line in log::wrap(&msg.spans_with_count(), size.x as usize) {
+                    if y >= size.y {
+                        return;
+                    }
+
+                    self.messages.print_spans(console, Point::new(0, y), &line, color, Colors::BLACK);
+                    y += 1;
+                }
             }
         });
     }
@@ -207,3 +413,141 @@ impl GameScreen {
         self.map_view.x + self.map.rect.width() <= game.world.map.width()
     }
 }
+
+/// Walks the player in a direction until they are blocked, a monster comes
+/// into view, or they reach something worth stopping for
+fn run(game: &mut Game, direction: Direction) {
+    for _ in 0..MAX_RUN_STEPS {
+        let before = game.world.player_pos();
+        game.do_command(Command::Walk(direction));
+
+        let pos = game.world.player_pos();
+        let map = &game.world.map;
+
+        let moved = pos != before;
+        let on_something = !map.items_at(pos).is_empty() || map.at(pos) != Tile::floor();
+
+        if !moved || monster_in_view(game) || on_something || game.world.is_player_dead() {
+            break;
+        }
+    }
+}
+
+/// Takes one step towards a point the player clicked on, returning false
+/// once they have arrived or have to stop
+fn travel_step(game: &mut Game, target: Point) -> bool {
+    let before = game.world.player_pos();
+
+    let next = match travel_path(game, target).and_then(|path| path.first().cloned()) {
+        Some(next) => next,
+        None => return false,
+    };
+
+    game.do_command(Command::Walk(Direction::from_offset(Offset::new(next.x - before.x, next.y - before.y))));
+
+    let pos = game.world.player_pos();
+    pos != before && pos != target && !monster_in_view(game) && !game.world.is_player_dead()
+}
+
+/// Finds the player a route to a point which only crosses places they have
+/// already seen
+fn travel_path(game: &Game, target: Point) -> Option<Vec<Point>> {
+    let world = &game.world;
+    let is_blocked = |p: Point| !world.map.is_explored(p) || world.entity_at(p).is_some();
+
+    path::find_path(&world.map, world.player_pos(), target, Costs::default(), is_blocked)
+}
+
+fn monster_in_view(game: &Game) -> bool {
+    let entities = &game.world.entities;
+
+    entities.ais.entities().into_iter()
+                           .filter_map(|entity| entities.position(entity))
+                           .any(|pos| game.world.map.is_visible(pos))
+}
+
+/// Describes what the player can see, or remembers, at a point on the map
+fn describe(game: &Game, pos: Point) -> Vec<String> {
+    let map = &game.world.map;
+
+    if !map.in_bounds(pos) || !map.is_explored(pos) {
+        return vec!["Unexplored".to_string()];
+    }
+
+    let mut lines = vec![];
+    let tile = capitalise(&map.at(pos).name().replace('_', " "));
+
+    if !map.is_visible(pos) {
+        lines.push(format!("{} (remembered)", tile));
+        return lines;
+    }
+
+    if let Some(entity) = game.world.entity_at(pos) {
+        let entities = &game.world.entities;
+
+        if entities.controlled.contains(entity) {
+            lines.push("You".to_string());
+        } else {
+            lines.push(format!("{} ({}/{})", entities.name(entity), entities.health(entity), entities.max_health(entity)));
+        }
+    }
+
+    let items = map.items_at(pos);
+    if let Some(item) = items.last() {
+        lines.push(item.name().to_string());
+
+        if items.len() > 1 {
+            lines.push(format!("and {} more", items.len() - 1));
+        }
+    }
+
+    lines.push(tile);
+    lines
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameScreen;
+    use engine::{log, Game};
+    use gui::{Color, Colors, HeadlessConsole};
+    use util::units::Size;
+
+    #[test]
+    fn renders_health_and_messages() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = GameScreen::new();
+
+        log::combat("You feel {red:watched}.");
+        screen.render(&mut game, &mut console);
+
+        let health = console.find("HP 100/100").expect("The health bar is not drawn");
+        assert_eq!(console.cell(health).map(|c| c.f_color), Some(Colors::WHITE));
+
+        let message = console.find("You feel watched.").expect("The message is not drawn");
+        assert_eq!(console.cell(message).map(|c| c.f_color), Some(Color::new(255, 255, 255)));
+        assert_eq!(console.cell(message.right(9)).map(|c| c.f_color), Some(Color::new(220, 50, 50)));
+    }
+
+    #[test]
+    fn shows_the_turn_above_the_time() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = GameScreen::new();
+
+        game.turn = 12;
+        screen.render(&mut game, &mut console);
+
+        let turn = console.find("Turn    12").expect("The turn is not drawn");
+        let time = console.find("Time").expect("The time is not drawn");
+        assert_eq!((time.x, time.y), (turn.x, turn.y + 1));
+    }
+}
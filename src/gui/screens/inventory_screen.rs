@@ -0,0 +1,128 @@
+use engine::{Command, Game, Slot};
+use gui::{primitives, Colors, Console, Widget};
+use gui::keymap::{self, Action, Context};
+use gui::screens::{Screen, ScreenChange};
+use util::units::{Point, Size};
+
+use std::cmp;
+
+/// Lists the items the player is carrying and wearing, and lets them be
+/// used, dropped or taken off
+pub struct InventoryScreen {
+    list: Widget,
+    selected: usize,
+}
+
+impl InventoryScreen {
+    pub fn new() -> Box<Screen> {
+        Box::new(
+            InventoryScreen {
+                list: Widget::new(Point::new(10, 5), Size::new(59, 39)),
+                selected: 0,
+            }
+        )
+    }
+
+    /// Keeps the selection on an item after the inventory has changed
+    fn clamp_selection(&mut self, game: &Game) {
+        let count = game.world.entities.items(game.world.player()).len();
+        self.selected = cmp::min(self.selected, count.saturating_sub(1));
+    }
+}
+
+impl Screen for InventoryScreen {
+    #[allow(unused)]
+    fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        if let Some(key) = console.check_for_keypress() {
+            let count = game.world.entities.items(game.world.player()).len();
+
+            match keymap::action(Context::Inventory, key) {
+                Some(Action::Up) => {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                Some(Action::Down) => {
+                    if self.selected + 1 < count {
+                        self.selected += 1;
+                    }
+                }
+                Some(Action::Use) if count > 0 => {
+                    game.do_command(Command::Use(self.selected));
+                }
+                Some(Action::Drop) if count > 0 => {
+                    game.do_command(Command::Drop(self.selected));
+                }
+                Some(Action::TakeOff(slot)) => {
+                    game.do_command(Command::Unequip(slot));
+                }
+                Some(Action::Back) => return Some(ScreenChange::RemoveScreen),
+                _ => {}
+            }
+
+            self.clamp_selection(game);
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        // Monsters act after every item used or dropped, so the player may
+        // not survive browsing their inventory
+        if game.world.is_player_dead() {
+            return Some(ScreenChange::RemoveScreen);
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn render(&mut self, game: &mut Game, console: &mut Console) {
+        let entities = &game.world.entities;
+        let player = game.world.player();
+        let inventory = entities.items(player);
+        let capacity = entities.inventories.get(player).map_or(0, |i| i.capacity);
+
+        primitives::draw_box_with_title(console, "Inventory", self.list.rect);
+
+        let carrying = format!("Carrying {}/{}", inventory.len(), capacity);
+        self.list.print_plain(console, Point::new(1, 0), &carrying);
+
+        if inventory.is_empty() {
+            self.list.print_plain(console, Point::new(1, 2), "You are not carrying anything.");
+        }
+
+        for (i, item) in inventory.iter().enumerate() {
+            let pos = Point::new(1, 2 + i as i32);
+            let letter = (b'a' + i as u8) as char;
+
+            if i == self.selected {
+                self.list.put_plain(console, pos, '>');
+            }
+
+            self.list.put(console, pos.right(2), item.glyph(), item.color(), Colors::BLACK);
+            self.list.print_plain(console, pos.right(4), &format!("{}) {}", letter, item.name()));
+        }
+
+        let bottom = self.list.rect.inner_size().y;
+        let equipment_y = bottom - 4 - Slot::all().len() as i32;
+
+        self.list.print(console, Point::new(1, equipment_y), "Wearing", Colors::GREY, Colors::BLACK);
+
+        for (i, &slot) in Slot::all().iter().enumerate() {
+            let pos = Point::new(1, equipment_y + 1 + i as i32);
+            let worn = entities.equipped(player, slot).map_or("-", |item| item.name());
+            let key = keymap::key_name(Context::Inventory, Action::TakeOff(slot));
+            self.list.print_plain(console, pos, &format!("{}) {:<7} {}", key, slot.name(), worn));
+        }
+
+        if let Some(item) = inventory.get(self.selected) {
+            self.list.print(console, Point::new(1, bottom - 2), &item.description(), Colors::LIGHT_GREY, Colors::BLACK);
+        }
+
+        let help = format!("{}: use/wear   {}: drop   {}: close",
+                           keymap::key_name(Context::Inventory, Action::Use),
+                           keymap::key_name(Context::Inventory, Action::Drop),
+                           keymap::key_name(Context::Inventory, Action::Back));
+        self.list.print(console, Point::new(1, bottom), &help, Colors::GREY, Colors::BLACK);
+    }
+}
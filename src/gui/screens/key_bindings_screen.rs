@@ -0,0 +1,224 @@
+use engine::Game;
+use gui::{primitives, Colors, Console, KeyCode, Widget};
+use gui::keymap::{self, Action, Context, Keymap};
+use gui::screens::{Screen, ScreenChange};
+use util::units::{Point, Size};
+
+/// Shows the key bound to every action and lets them be changed.
+///
+/// This screen always uses the arrow keys, Enter and Escape, whatever they
+/// are bound to elsewhere, and it never leaves the menus or the pause key
+/// without a key, so that no set of bindings can lock the player out of it.
+/// Every change is saved straight away.
+pub struct KeyBindingsScreen {
+    list: Widget,
+    keymap: Keymap,
+    context: usize,
+    selected: usize,
+    /// Set while waiting for the key to bind to the selected action
+    binding: bool,
+    /// Shown when saving the bindings fails
+    error: Option<String>,
+}
+
+impl KeyBindingsScreen {
+    pub fn new() -> Box<Screen> {
+        Box::new(
+            KeyBindingsScreen {
+                list: Widget::new(Point::new(10, 5), Size::new(59, 39)),
+                keymap: keymap::current(),
+                context: 0,
+                selected: 0,
+                binding: false,
+                error: None,
+            }
+        )
+    }
+
+    fn context(&self) -> Context {
+        Context::all()[self.context]
+    }
+
+    fn action_name(&self, action: Action) -> String {
+        let context = self.context();
+        let name = context.actions().iter().find(|&&(_, a)| a == action).map_or("", |&(name, _)| name);
+        name.replace('_', " ")
+    }
+
+    /// Makes the edited bindings current and saves them for next time
+    fn apply(&mut self) {
+        keymap::set(self.keymap.clone());
+
+        self.error = match self.keymap.save(keymap::DEFAULT_PATH) {
+            Ok(()) => None,
+            Err(e) => Some(format!("Could not save key bindings: {}", e)),
+        };
+    }
+}
+
+impl Screen for KeyBindingsScreen {
+    #[allow(unused)]
+    fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        if let Some(key) = console.check_for_keypress() {
+            let context = self.context();
+            let actions = context.actions();
+
+            if self.binding {
+                self.binding = false;
+
+                // Escape is never bound here, so that binding can always be
+                // cancelled
+                if key.code == KeyCode::Escape {
+                    return None;
+                }
+
+                let previous = self.keymap.action(context, key);
+
+                if self.keymap.bind(context, actions[self.selected].1, key) {
+                    self.apply();
+                } else if let Some(previous) = previous {
+                    let name = self.action_name(previous);
+                    self.error = Some(format!("{} is the only key for \"{}\"", key.name(), name));
+                }
+
+                return None;
+            }
+
+            match key.code {
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    if self.selected + 1 < actions.len() {
+                        self.selected += 1;
+                    }
+                }
+                KeyCode::Left | KeyCode::Right => {
+                    let count = Context::all().len();
+                    self.context = if key.code == KeyCode::Left { (self.context + count - 1) % count }
+                                   else { (self.context + 1) % count };
+                    self.selected = 0;
+                }
+                KeyCode::Enter => self.binding = true,
+                KeyCode::Char('c') if key.modifiers.is_empty() => {
+                    let action = actions[self.selected].1;
+
+                    if self.keymap.clear(context, action) {
+                        self.apply();
+                    } else {
+                        self.error = Some(format!("\"{}\" must always have a key", self.action_name(action)));
+                    }
+                }
+                KeyCode::Char('r') if key.modifiers.is_empty() => {
+                    self.keymap = Keymap::builtin();
+                    self.apply();
+                }
+                KeyCode::Escape => return Some(ScreenChange::RemoveScreen),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        None
+    }
+
+    #[allow(unused)]
+    fn render(&mut self, game: &mut Game, console: &mut Console) {
+        primitives::draw_box_with_title(console, "Key Bindings", self.list.rect);
+
+        let mut x = 1;
+        for (i, context) in Context::all().iter().enumerate() {
+            let name = context.name();
+
+            if i == self.context {
+                self.list.print(console, Point::new(x, 0), name, Colors::BLACK, Colors::LIGHT_GREY);
+            } else {
+                self.list.print(console, Point::new(x, 0), name, Colors::GREY, Colors::BLACK);
+            }
+
+            x += name.len() as i32 + 2;
+        }
+
+        let context = self.context();
+
+        for (i, &(name, action)) in context.actions().iter().enumerate() {
+            let pos = Point::new(1, 2 + i as i32);
+
+            if i == self.selected {
+                self.list.put_plain(console, pos, '>');
+            }
+
+            let keys: Vec<String> = self.keymap.keys(context, action).iter().map(|k| k.name()).collect();
+            let line = format!("{:<18}{}", name.replace('_', " "), keys.join(" "));
+            self.list.print_plain(console, pos.right(2), &line);
+        }
+
+        let bottom = self.list.rect.inner_size().y;
+
+        if self.binding {
+            let name = context.actions()[self.selected].0.replace('_', " ");
+            let prompt = format!("Press a key for \"{}\", or Escape to cancel", name);
+            self.list.print(console, Point::new(1, bottom - 2), &prompt, Colors::WHITE, Colors::BLACK);
+        } else if let Some(ref error) = self.error {
+            self.list.print(console, Point::new(1, bottom - 2), error, Colors::RED, Colors::BLACK);
+        }
+
+        self.list.print(console, Point::new(1, bottom), "Left/Right: section  Enter: add key  c: clear  r: reset",
+                        Colors::GREY, Colors::BLACK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyBindingsScreen;
+    use engine::Game;
+    use gui::{HeadlessConsole, Key, KeyCode, KeyModifiers};
+    use gui::keymap::{self, Action, Context};
+    use gui::screens::Screen;
+    use util::units::{Direction, Size};
+
+    fn press(screen: &mut Box<Screen>, console: &mut HeadlessConsole, key: Key) {
+        console.press(key);
+        assert!(screen.input(&mut Game::with_seed(1), console).is_none());
+    }
+
+    #[test]
+    fn escape_cancels_binding() {
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = KeyBindingsScreen::new();
+
+        press(&mut screen, &mut console, Key::new(KeyCode::Enter));
+        press(&mut screen, &mut console, Key::new(KeyCode::Escape));
+
+        assert_eq!(keymap::action(Context::Menu, Key::new(KeyCode::Escape)), Some(Action::Back));
+        assert_eq!(keymap::current().keys(Context::Menu, Action::Up).len(), 2);
+    }
+
+    #[test]
+    fn refuses_to_clear_the_menu_keys() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = KeyBindingsScreen::new();
+
+        press(&mut screen, &mut console, Key::new(KeyCode::Char('c')));
+        screen.render(&mut game, &mut console);
+
+        assert!(console.find("\"up\" must always have a key").is_some());
+        assert!(!keymap::current().keys(Context::Menu, Action::Up).is_empty());
+    }
+
+    #[test]
+    fn ignores_letters_with_modifiers() {
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = KeyBindingsScreen::new();
+        let ctrl = KeyModifiers { shift: false, ctrl: true, alt: false };
+
+        press(&mut screen, &mut console, Key::new(KeyCode::Right));
+        press(&mut screen, &mut console, Key::with_modifiers(KeyCode::Char('c'), ctrl));
+        press(&mut screen, &mut console, Key::with_modifiers(KeyCode::Char('r'), ctrl));
+
+        assert!(!keymap::current().keys(Context::Game, Action::Walk(Direction::Up)).is_empty());
+    }
+}
@@ -1,11 +1,19 @@
-use engine::Game;
-use gui::{Console, Key, Menu, MenuOption};
+use engine::{log, save, Game};
+use gui::{Colors, Console, Menu, MenuOption};
+use gui::keymap::{self, Action, Context};
 use gui::screens::{self, Screen, ScreenChange};
 use util::units::Point;
 
+use std::cmp;
+
 #[allow(missing_copy_implementations)]
 pub struct MenuScreen {
     menu: Menu<MainMenu>,
+    /// Shown beneath the menu when loading a saved game fails
+    error: Option<String>,
+    /// Where to record the game once the player starts it. Nothing is
+    /// recorded for a game continued from a save.
+    record_path: Option<String>,
 }
 
 static LOGO: &'static str =
@@ -20,17 +28,28 @@ static LOGO: &'static str =
 
 enum MainMenu {
     StartGame,
+    Continue,
+    KeyBindings,
     Exit,
 }
 
 impl MenuScreen {
     pub fn new() -> Box<Screen> {
+        MenuScreen::recording_to(None)
+    }
+
+    /// A menu which records the game to `path` if the player starts it
+    pub fn recording_to(path: Option<String>) -> Box<Screen> {
         Box::new(
             MenuScreen {
                 menu: Menu::new(vec![
                                     MenuOption("Start Game", MainMenu::StartGame),
+                                    MenuOption("Continue", MainMenu::Continue),
+                                    MenuOption("Key Bindings", MainMenu::KeyBindings),
                                     MenuOption("Exit Game", MainMenu::Exit),
                                 ]),
+                error: None,
+                record_path: path,
             }
         )
     }
@@ -40,16 +59,36 @@ impl Screen for MenuScreen {
     #[allow(unused)]
     fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
         if let Some(key) = console.check_for_keypress() {
-            match key {
-                Key::Up => {
+            match keymap::action(Context::Menu, key) {
+                Some(Action::Up) => {
                     self.menu.prev();
                 }
-                Key::Down => {
+                Some(Action::Down) => {
                     self.menu.next();
                 }
-                Key::Enter => {
+                Some(Action::Select) => {
                     match *self.menu.selected().option() {
-                        MainMenu::StartGame => return Some(ScreenChange::AddScreen(screens::GameScreen::new())),
+                        MainMenu::StartGame => {
+                            if let Some(path) = self.record_path.take() {
+                                if let Err(e) = game.record_to(&path) {
+                                    log::error(&format!("Could not record the game to {}: {}", path, e));
+                                }
+                            }
+
+                            return Some(ScreenChange::AddScreen(screens::GameScreen::new()));
+                        },
+                        MainMenu::Continue => {
+                            match Game::load(save::DEFAULT_PATH) {
+                                Ok(loaded) => {
+                                    *game = loaded;
+                                    self.error = None;
+                                    self.record_path = None;
+                                    return Some(ScreenChange::AddScreen(screens::GameScreen::new()));
+                                }
+                                Err(e) => self.error = Some(format!("Could not continue: {}", e)),
+                            }
+                        },
+                        MainMenu::KeyBindings => return Some(ScreenChange::AddScreen(screens::KeyBindingsScreen::new())),
                         MainMenu::Exit => return Some(ScreenChange::ExitGame),
                     }
                 },
@@ -79,5 +118,49 @@ impl Screen for MenuScreen {
                 console.put_plain(menu_loc.down(i as i32), '>');
             }
         }
+
+        if let Some(ref error) = self.error {
+            let error_x = cmp::max(0, console.size().x / 2 - error.len() as i32 / 2);
+            let error_loc = Point::new(error_x, menu_loc.y + self.menu.items().count() as i32 + 2);
+            console.print(error_loc, error, Colors::RED, Colors::BLACK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MenuScreen;
+    use engine::Game;
+    use gui::{Console, HeadlessConsole, Key, KeyCode};
+    use util::units::{Point, Size};
+
+    #[test]
+    fn renders_options_with_first_selected() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = MenuScreen::new();
+
+        screen.render(&mut game, &mut console);
+
+        let start = console.find("Start Game").expect("Start Game is not drawn");
+        assert_eq!(console.cell(start.left(2)).map(|c| c.c), Some('>'));
+        assert!(console.find("Continue").is_some());
+        assert!(console.find("Exit Game").is_some());
+    }
+
+    #[test]
+    fn moving_down_moves_the_selection() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+        let mut screen = MenuScreen::new();
+
+        console.press(Key::new(KeyCode::Down));
+        screen.input(&mut game, &mut console);
+        console.clear();
+        screen.render(&mut game, &mut console);
+
+        let continue_pos = console.find("Continue").expect("Continue is not drawn");
+        assert_eq!(console.cell(continue_pos.left(2)).map(|c| c.c), Some('>'));
+        assert_eq!(console.cell(Point::new(continue_pos.x - 2, continue_pos.y - 1)).map(|c| c.c), Some(' '));
     }
 }
@@ -0,0 +1,377 @@
+use engine::Game;
+use engine::log::{self, Span};
+use gui::{primitives, Color, Colors, Console, KeyCode, Widget};
+use gui::keymap::{self, Action, Context};
+use gui::screens::{Screen, ScreenChange};
+use util::units::{Point, Size};
+
+use std::cmp;
+
+/// The width of the turn column, including the space after it
+const TURN_WIDTH: i32 = 6;
+
+/// One row of the history, which is either the start of a message or a
+/// line it wrapped onto
+struct Line {
+    /// The turn the message was written on, shown on its first line only
+    turn: Option<u32>,
+    spans: Vec<Span>,
+    color: Color,
+    /// Which message the line belongs to, counting from the oldest
+    message: usize,
+}
+
+/// Shows the messages in the log, oldest at the top, and lets the player
+/// scroll through them and search for text.
+///
+/// The history opens at the newest messages, including those in categories
+/// the log hides, and can be switched to show only what the log shows.
+/// Searching looks backwards from the newest message, so the next match is
+/// always an older one.
+pub struct MessageHistoryScreen {
+    view: Widget,
+    /// Whether messages the log's filter hides are shown too
+    all: bool,
+    lines: Vec<Line>,
+    /// The plain text of each message, lowercased for searching
+    texts: Vec<String>,
+    /// The first line shown
+    scroll: usize,
+    /// What is being typed, while the player is entering a search
+    typing: Option<String>,
+    /// What was last searched for
+    search: String,
+    /// The first line of every message matching the search
+    matches: Vec<usize>,
+    /// Which of `matches` was jumped to last
+    current: Option<usize>,
+}
+
+impl MessageHistoryScreen {
+    pub fn new() -> Box<Screen> {
+        let mut screen = MessageHistoryScreen {
+            view: Widget::new(Point::new(0, 1), Size::new(78, 48)),
+            all: true,
+            lines: vec![],
+            texts: vec![],
+            scroll: 0,
+            typing: None,
+            search: String::new(),
+            matches: vec![],
+            current: None,
+        };
+        screen.load();
+
+        Box::new(screen)
+    }
+
+    /// Reads the messages from the log, scrolling to the newest and
+    /// searching them again
+    fn load(&mut self) {
+        let width = (self.view.inner_size().x - TURN_WIDTH) as usize;
+        let all = self.all;
+
+        self.lines.clear();
+        self.texts.clear();
+
+        let lines = &mut self.lines;
+        let texts = &mut self.texts;
+
+        log::LOG.with(|l| {
+            let log = l.borrow();
+            let messages: Vec<_> = log.items().filter(|m| all || log.filter.shows(m.category())).collect();
+
+            for (i, message) in messages.iter().rev().enumerate() {
+                let color = message.category().color();
+
+                for (j, spans) in log::wrap(&message.spans_with_count(), width).into_iter().enumerate() {
+                    let turn = if j == 0 { Some(message.turn()) } else { None };
+                    lines.push(Line { turn: turn, spans: spans, color: color, message: i });
+                }
+
+                texts.push(message.text().to_lowercase());
+            }
+        });
+
+        self.scroll = self.max_scroll();
+
+        let search = self.search.clone();
+        self.find(&search);
+    }
+
+    /// The number of lines shown at once, leaving room for the status,
+    /// filter and help lines at the bottom
+    fn page_height(&self) -> usize {
+        cmp::max(self.view.inner_size().y - 3, 1) as usize
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.page_height())
+    }
+
+    fn scroll_to(&mut self, scroll: usize) {
+        self.scroll = cmp::min(scroll, self.max_scroll());
+    }
+
+    /// Finds every message containing the search, ignoring case, and jumps
+    /// to the newest of them
+    fn find(&mut self, search: &str) {
+        self.search = search.to_string();
+        self.matches.clear();
+        self.current = None;
+
+        if search.is_empty() {
+            return;
+        }
+
+        let search = search.to_lowercase();
+        let mut previous = None;
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if previous != Some(line.message) && self.texts[line.message].contains(&search) {
+                self.matches.push(i);
+            }
+
+            previous = Some(line.message);
+        }
+
+        if !self.matches.is_empty() {
+            let newest = self.matches.len() - 1;
+            self.jump_to(newest);
+        }
+    }
+
+    /// Scrolls a match into the middle of the page
+    fn jump_to(&mut self, index: usize) {
+        self.current = Some(index);
+        let line = self.matches[index];
+        let half = self.page_height() / 2;
+        self.scroll_to(line.saturating_sub(half));
+    }
+
+    /// Moves to the next older match, or the next newer one, wrapping
+    /// around at either end
+    fn step_match(&mut self, older: bool) {
+        let count = self.matches.len();
+
+        if count == 0 {
+            return;
+        }
+
+        let index = match self.current {
+            Some(current) if older => (current + count - 1) % count,
+            Some(current) => (current + 1) % count,
+            None => count - 1,
+        };
+
+        self.jump_to(index);
+    }
+
+    /// Handles a key while a search is being typed
+    fn type_key(&mut self, code: KeyCode, plain: bool) {
+        let mut typing = match self.typing.take() {
+            Some(typing) => typing,
+            None => return,
+        };
+
+        match code {
+            KeyCode::Enter => {
+                self.find(&typing);
+                return;
+            }
+            KeyCode::Escape => {
+                let search = self.search.clone();
+                self.find(&search);
+                return;
+            }
+            KeyCode::Backspace => { typing.pop(); }
+            KeyCode::Char(c) if plain => typing.push(c),
+            _ => {}
+        }
+
+        // Searching as the player types shows straight away whether there
+        // is anything to find
+        let search = self.search.clone();
+        self.find(&typing);
+        self.search = search;
+        self.typing = Some(typing);
+    }
+
+    /// Describes the search, or which lines are showing if there isn't one
+    fn status(&self) -> String {
+        if let Some(ref typing) = self.typing {
+            return format!("Search: {}_", typing);
+        }
+
+        if !self.search.is_empty() {
+            return match self.current {
+                Some(current) => format!("Match {} of {} for \"{}\"", self.matches.len() - current,
+                                         self.matches.len(), self.search),
+                None => format!("No messages contain \"{}\"", self.search),
+            };
+        }
+
+        if self.lines.is_empty() {
+            return "There are no messages.".to_string();
+        }
+
+        let last = cmp::min(self.scroll + self.page_height(), self.lines.len());
+        format!("Lines {}-{} of {}", self.scroll + 1, last, self.lines.len())
+    }
+}
+
+impl Screen for MessageHistoryScreen {
+    #[allow(unused)]
+    fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        if let Some(key) = console.check_for_keypress() {
+            if self.typing.is_some() {
+                let plain = !key.modifiers.ctrl && !key.modifiers.alt;
+                self.type_key(key.code, plain);
+                return None;
+            }
+
+            let page = self.page_height();
+
+            match keymap::action(Context::History, key) {
+                Some(Action::Up) => self.scroll = self.scroll.saturating_sub(1),
+                Some(Action::Down) => {
+                    let scroll = self.scroll + 1;
+                    self.scroll_to(scroll);
+                }
+                Some(Action::PageUp) => self.scroll = self.scroll.saturating_sub(page),
+                Some(Action::PageDown) => {
+                    let scroll = self.scroll + page;
+                    self.scroll_to(scroll);
+                }
+                Some(Action::Top) => self.scroll = 0,
+                Some(Action::Bottom) => self.scroll = self.max_scroll(),
+                Some(Action::Search) => self.typing = Some(String::new()),
+                Some(Action::NextMatch) => self.step_match(true),
+                Some(Action::PreviousMatch) => self.step_match(false),
+                Some(Action::ToggleFilter) => {
+                    self.all = !self.all;
+                    self.load();
+                }
+                Some(Action::Back) => return Some(ScreenChange::RemoveScreen),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        None
+    }
+
+    #[allow(unused)]
+    fn render(&mut self, game: &mut Game, console: &mut Console) {
+        primitives::draw_box_with_title(console, "Message History", self.view.rect);
+
+        let current = self.current.map(|i| self.lines[self.matches[i]].message);
+
+        for (y, line) in self.lines.iter().skip(self.scroll).take(self.page_height()).enumerate() {
+            let pos = Point::new(0, y as i32);
+            let is_match = self.matches.iter().any(|&m| self.lines[m].message == line.message);
+
+            let background = if current == Some(line.message) {
+                Colors::DARK_GREY
+            } else if is_match {
+                Colors::DARKEST_GREY
+            } else {
+                Colors::BLACK
+            };
+
+            if let Some(turn) = line.turn {
+                self.view.print(console, pos, &format!("{:>5}", turn), Colors::GREY, Colors::BLACK);
+            }
+
+            self.view.print_spans(console, pos.right(TURN_WIDTH), &line.spans, line.color, background);
+        }
+
+        let bottom = self.view.rect.inner_size().y;
+
+        self.view.print(console, Point::new(1, bottom - 2), &self.status(), Colors::WHITE, Colors::BLACK);
+
+        let filter = format!("{}: {}", keymap::key_name(Context::History, Action::ToggleFilter),
+                             if self.all { "showing every message" } else { "showing what the log shows" });
+        self.view.print(console, Point::new(1, bottom - 1), &filter, Colors::GREY, Colors::BLACK);
+
+        let help = if self.typing.is_some() {
+            "Enter: done   Escape: cancel".to_string()
+        } else {
+            format!("{}/{}: page   {}: search   {}/{}: older/newer match   {}: close",
+                    keymap::key_name(Context::History, Action::PageUp),
+                    keymap::key_name(Context::History, Action::PageDown),
+                    keymap::key_name(Context::History, Action::Search),
+                    keymap::key_name(Context::History, Action::NextMatch),
+                    keymap::key_name(Context::History, Action::PreviousMatch),
+                    keymap::key_name(Context::History, Action::Back))
+        };
+        self.view.print(console, Point::new(1, bottom), &help, Colors::GREY, Colors::BLACK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageHistoryScreen;
+    use engine::{log, Game};
+    use gui::{Colors, Console, HeadlessConsole, Key, KeyCode};
+    use util::units::Size;
+
+    #[test]
+    fn shows_hidden_categories_until_filtered() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+
+        log::combat("You hit the rat.");
+        log::debug("The rat rolled a 3.");
+        let mut screen = MessageHistoryScreen::new();
+
+        screen.render(&mut game, &mut console);
+        assert!(console.find("The rat rolled a 3.").is_some());
+        assert!(console.find("showing every message").is_some());
+
+        console.press(Key::new(KeyCode::Char('f')));
+        screen.input(&mut game, &mut console);
+        console.clear();
+        screen.render(&mut game, &mut console);
+
+        assert!(console.find("You hit the rat.").is_some());
+        assert!(console.find("The rat rolled a 3.").is_none());
+        assert!(console.find("showing what the log shows").is_some());
+    }
+
+    #[test]
+    fn an_empty_search_clears_the_last_one() {
+        let mut game = Game::with_seed(1);
+        let mut console = HeadlessConsole::new(Size::new(80, 50));
+
+        log::combat("You hit the rat.");
+        let mut screen = MessageHistoryScreen::new();
+
+        for &code in &[KeyCode::Char('/'), KeyCode::Char('h'), KeyCode::Char('i'), KeyCode::Char('t'), KeyCode::Enter] {
+            console.press(Key::new(code));
+            screen.input(&mut game, &mut console);
+        }
+
+        screen.render(&mut game, &mut console);
+        let status = console.find("Match 1 of 1 for \"hit\"").expect("The match is not shown");
+        let filter = console.find("showing every message").expect("The filter is not shown");
+        assert!(filter.y != status.y);
+
+        for &code in &[KeyCode::Char('/'), KeyCode::Enter, KeyCode::Char('n')] {
+            console.press(Key::new(code));
+            screen.input(&mut game, &mut console);
+        }
+
+        console.clear();
+        screen.render(&mut game, &mut console);
+        assert!(console.find("Lines 1-2 of 2").is_some());
+        assert!(console.find("Match").is_none());
+
+        let hit = console.find("You hit the rat.").expect("The message is not shown");
+        assert_eq!(console.cell(hit).map(|c| c.b_color), Some(Colors::BLACK));
+    }
+}
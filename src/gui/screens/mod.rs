@@ -1,13 +1,23 @@
 use engine::Game;
 use gui::Console;
 
+mod game_over_screen;
 mod game_screen;
+mod inventory_screen;
+mod key_bindings_screen;
 mod menu_screen;
+mod message_history_screen;
 mod pause_screen;
+mod replay_screen;
 
+pub use self::game_over_screen::GameOverScreen;
 pub use self::game_screen::GameScreen;
+pub use self::inventory_screen::InventoryScreen;
+pub use self::key_bindings_screen::KeyBindingsScreen;
 pub use self::menu_screen::MenuScreen;
+pub use self::message_history_screen::MessageHistoryScreen;
 pub use self::pause_screen::PauseScreen;
+pub use self::replay_screen::ReplayScreen;
 
 pub trait Screen {
     fn input(&mut self, &mut Game, &mut Console) -> Option<ScreenChange>;
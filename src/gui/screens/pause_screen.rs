@@ -1,6 +1,8 @@
-use engine::Game;
-use gui::{Console, Key, Menu, MenuOption};
-use gui::screens::{Screen, ScreenChange};
+use engine::{save, Game};
+use engine::log;
+use gui::{Console, Menu, MenuOption};
+use gui::keymap::{self, Action, Context};
+use gui::screens::{self, Screen, ScreenChange};
 use util::units::Point;
 
 pub struct PauseScreen {
@@ -9,6 +11,8 @@ pub struct PauseScreen {
 
 enum PauseMenu {
     Resume,
+    Save,
+    KeyBindings,
     Exit,
 }
 
@@ -18,6 +22,8 @@ impl PauseScreen {
             PauseScreen {
                 menu: Menu::new(vec![
                                     MenuOption("Resume Game", PauseMenu::Resume),
+                                    MenuOption("Save Game", PauseMenu::Save),
+                                    MenuOption("Key Bindings", PauseMenu::KeyBindings),
                                     MenuOption("Exit Game", PauseMenu::Exit),
                                 ]),
             }
@@ -29,20 +35,28 @@ impl Screen for PauseScreen {
     #[allow(unused)]
     fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
         if let Some(key) = console.check_for_keypress() {
-            match key {
-                Key::Up => {
+            match keymap::action(Context::Menu, key) {
+                Some(Action::Up) => {
                     self.menu.prev();
                 }
-                Key::Down => {
+                Some(Action::Down) => {
                     self.menu.next();
                 }
-                Key::Enter => {
+                Some(Action::Select) => {
                     match *self.menu.selected().option() {
                         PauseMenu::Resume => return Some(ScreenChange::RemoveScreen),
+                        PauseMenu::Save => {
+                            match game.save(save::DEFAULT_PATH) {
+                                Ok(()) => log::system("Game saved."),
+                                Err(e) => log::error(&format!("Could not save the game: {}", e)),
+                            }
+                            return Some(ScreenChange::RemoveScreen);
+                        },
+                        PauseMenu::KeyBindings => return Some(ScreenChange::AddScreen(screens::KeyBindingsScreen::new())),
                         PauseMenu::Exit => return Some(ScreenChange::ExitGame),
                     }
                 },
-                Key::Escape => return Some(ScreenChange::RemoveScreen),
+                Some(Action::Back) => return Some(ScreenChange::RemoveScreen),
                 _ => {}
             }
         }
@@ -59,6 +73,12 @@ impl Screen for PauseScreen {
     fn render(&mut self, game: &mut Game, console: &mut Console) {
         console.print_plain(Point::new(0, 0), "Paused");
 
+        let seed = match game.seed {
+            Some(seed) => format!("Seed: {}", seed),
+            None => "Seed: unknown".to_string(),
+        };
+        console.print_plain(Point::new(0, console.size().y - 1), &seed);
+
         let longest_item = self.menu.items()
                                     .max_by_key(|item| item.text().len())
                                     .expect("No Items found")
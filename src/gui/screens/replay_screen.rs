@@ -0,0 +1,108 @@
+use engine::{Game, Replay};
+use gui::{Colors, Console};
+use gui::keymap::{self, Action, Context};
+use gui::screens::{self, Screen, ScreenChange};
+use util::units::Point;
+
+use std::time::{Duration, Instant};
+
+/// How many commands are played back each second at each speed
+static SPEEDS: [u64; 6] = [1, 2, 5, 10, 30, 60];
+
+/// Plays back a recorded game, drawing it the same way as it was played
+pub struct ReplayScreen {
+    game_screen: Box<Screen>,
+    replay: Replay,
+    speed: usize,
+    paused: bool,
+    last_step: Instant,
+    /// Set when the replay could not be played to the end
+    error: Option<String>,
+}
+
+impl ReplayScreen {
+    pub fn new(replay: Replay) -> Box<Screen> {
+        Box::new(
+            ReplayScreen {
+                game_screen: screens::GameScreen::new(),
+                replay: replay,
+                speed: 2,
+                paused: false,
+                last_step: Instant::now(),
+                error: None,
+            }
+        )
+    }
+
+    fn step(&mut self, game: &mut Game) {
+        if let Err(e) = self.replay.step(game) {
+            self.error = Some(e.to_string());
+        }
+
+        self.last_step = Instant::now();
+    }
+
+    fn can_step(&self) -> bool {
+        self.error.is_none() && !self.replay.is_finished()
+    }
+}
+
+impl Screen for ReplayScreen {
+    #[allow(unused)]
+    fn input(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        if let Some(key) = console.check_for_keypress() {
+            match keymap::action(Context::Replay, key) {
+                Some(Action::Back) => return Some(ScreenChange::ExitGame),
+                Some(Action::TogglePause) => self.paused = !self.paused,
+                Some(Action::Faster) => {
+                    if self.speed + 1 < SPEEDS.len() {
+                        self.speed += 1;
+                    }
+                }
+                Some(Action::Slower) => self.speed = self.speed.saturating_sub(1),
+                Some(Action::Step) if self.paused && self.can_step() => self.step(game),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn update(&mut self, game: &mut Game, console: &mut Console) -> Option<ScreenChange> {
+        let interval = Duration::from_millis(1000 / SPEEDS[self.speed]);
+
+        if !self.paused && self.can_step() && self.last_step.elapsed() >= interval {
+            self.step(game);
+        }
+
+        None
+    }
+
+    #[allow(unused)]
+    fn render(&mut self, game: &mut Game, console: &mut Console) {
+        self.game_screen.render(game, console);
+
+        let state = if let Some(ref error) = self.error {
+            error.clone()
+        } else if self.replay.is_finished() {
+            "finished".to_string()
+        } else if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{}/s", SPEEDS[self.speed])
+        };
+
+        let status = format!(" Replay {}/{} {} ", self.replay.position(), self.replay.len(), state);
+        let help = format!(" {}: pause  {}/{}: speed  {}: step  {}: quit ",
+                           keymap::key_name(Context::Replay, Action::TogglePause),
+                           keymap::key_name(Context::Replay, Action::Faster),
+                           keymap::key_name(Context::Replay, Action::Slower),
+                           keymap::key_name(Context::Replay, Action::Step),
+                           keymap::key_name(Context::Replay, Action::Back));
+
+        let height = console.size().y;
+        console.print(Point::new(0, height - 1), &status, Colors::BLACK, Colors::LIGHT_GREY);
+        console.print(Point::new(status.len() as i32 + 1, height - 1), &help, Colors::LIGHT_GREY, Colors::BLACK);
+    }
+}
@@ -0,0 +1,208 @@
+extern crate tcod_sys;
+
+use gui::{Alignment, Color, Console, Key, KeyCode, KeyModifiers, MouseButton, MouseEvent};
+use util::units::{AsTuple, Point, Size};
+
+use tcod;
+use tcod::Console as TCODConsole;
+use tcod::input::{self, Event};
+use tcod::input::Key as TKey;
+use tcod::input::KeyCode as TKeyCode;
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// A console drawn in a window by libtcod
+pub struct TcodConsole {
+    console: tcod::RootConsole,
+    size: Size,
+    /// Keys and mouse events which have happened but not been asked for
+    keys: VecDeque<Key>,
+    mouse: VecDeque<MouseEvent>,
+    /// The cell the mouse was last seen over
+    mouse_pos: Option<Point>,
+}
+
+impl TcodConsole {
+    pub fn new(size: Size) -> TcodConsole {
+        tcod::system::set_fps(60);
+
+        let (width, height) = size.as_tuple();
+        let console = tcod::RootConsole::initializer()
+                        .size(width, height)
+                        .title("Verbonia")
+                        .font(Path::new("assets/fonts/font.png"), tcod::FontLayout::AsciiInRow)
+                        .font_type(tcod::FontType::Greyscale)
+                        .font_dimensions(32, 64)
+                        .init();
+
+        unsafe {
+            // tcod_sys::TCOD_console_map_ascii_code_to_font(64, 1, 1);
+        }
+
+        TcodConsole {
+            console: console,
+            size: size,
+            keys: VecDeque::new(),
+            mouse: VecDeque::new(),
+            mouse_pos: None,
+        }
+    }
+
+    /// Reads whatever has happened since the last check into the queues.
+    ///
+    /// libtcod only reports keys and the mouse together, so both are read
+    /// at once and kept until they are asked for.
+    fn poll(&mut self) {
+        match input::check_for_event(input::KEY_PRESS | input::MOUSE) {
+            Some((_, Event::Key(keypress))) => {
+                if let Some(key) = convert_key(keypress) {
+                    self.keys.push_back(key);
+                }
+            }
+            Some((_, Event::Mouse(mouse))) => {
+                let pos = Point::new(mouse.cx as i32, mouse.cy as i32);
+
+                if self.mouse_pos != Some(pos) {
+                    self.mouse_pos = Some(pos);
+                    self.mouse.push_back(MouseEvent::Moved(pos));
+                }
+
+                let buttons = [
+                    (mouse.lbutton_pressed, MouseButton::Left),
+                    (mouse.mbutton_pressed, MouseButton::Middle),
+                    (mouse.rbutton_pressed, MouseButton::Right),
+                ];
+
+                for &(pressed, button) in buttons.iter() {
+                    if pressed {
+                        self.mouse.push_back(MouseEvent::Pressed(button, pos));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl Console for TcodConsole {
+    fn put_plain(&mut self, pos: Point, c: char) {
+        self.console.put_char(pos.x, pos.y, c, tcod::BackgroundFlag::None);
+    }
+
+    fn put(&mut self, pos: Point, c: char, f_color: Color, b_color: Color) {
+        self.console.put_char_ex(pos.x, pos.y, c, to_tcod(f_color), to_tcod(b_color));
+    }
+
+    fn print_plain(&mut self, pos: Point, text: &str) {
+        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::None, tcod::TextAlignment::Left, text);
+    }
+
+    fn print(&mut self, pos: Point, text: &str, f_color: Color, b_color: Color) {
+        self.console.set_default_background(to_tcod(b_color));
+        self.console.set_default_foreground(to_tcod(f_color));
+        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::Set, tcod::TextAlignment::Left, text);
+        self.console.set_default_background(tcod::Color::new(29, 29, 29));
+        self.console.set_default_foreground(tcod::colors::WHITE);
+    }
+
+    fn print_align(&mut self, pos: Point, text: &str, alignment: Alignment) {
+        let alignment = match alignment {
+            Alignment::Left => tcod::TextAlignment::Left,
+            Alignment::Right => tcod::TextAlignment::Right,
+            Alignment::Center => tcod::TextAlignment::Center,
+        };
+
+        self.console.print_ex(pos.x, pos.y, tcod::BackgroundFlag::None, alignment, text);
+    }
+
+    fn clear(&mut self) {
+        self.console.clear();
+    }
+
+    fn flush(&mut self) {
+        self.console.flush();
+    }
+
+    fn check_for_keypress(&mut self) -> Option<Key> {
+        if self.keys.is_empty() {
+            self.poll();
+        }
+
+        self.keys.pop_front()
+    }
+
+    fn check_for_mouse(&mut self) -> Option<MouseEvent> {
+        if self.mouse.is_empty() {
+            self.poll();
+        }
+
+        self.mouse.pop_front()
+    }
+
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn window_closed(&self) -> bool {
+        self.console.window_closed()
+    }
+}
+
+fn to_tcod(color: Color) -> tcod::Color {
+    tcod::Color::new(color.r, color.g, color.b)
+}
+
+fn convert_key(keypress: TKey) -> Option<Key> {
+    let code = match keypress.code {
+        TKeyCode::Up => KeyCode::Up,
+        TKeyCode::Down => KeyCode::Down,
+        TKeyCode::Left => KeyCode::Left,
+        TKeyCode::Right => KeyCode::Right,
+        TKeyCode::Escape => KeyCode::Escape,
+        TKeyCode::Enter | TKeyCode::NumPadEnter => KeyCode::Enter,
+        TKeyCode::Tab => KeyCode::Tab,
+        TKeyCode::Backspace => KeyCode::Backspace,
+        TKeyCode::Delete => KeyCode::Delete,
+        TKeyCode::Insert => KeyCode::Insert,
+        TKeyCode::Home => KeyCode::Home,
+        TKeyCode::End => KeyCode::End,
+        TKeyCode::PageUp => KeyCode::PageUp,
+        TKeyCode::PageDown => KeyCode::PageDown,
+        TKeyCode::F1 => KeyCode::F(1),
+        TKeyCode::F2 => KeyCode::F(2),
+        TKeyCode::F3 => KeyCode::F(3),
+        TKeyCode::F4 => KeyCode::F(4),
+        TKeyCode::F5 => KeyCode::F(5),
+        TKeyCode::F6 => KeyCode::F(6),
+        TKeyCode::F7 => KeyCode::F(7),
+        TKeyCode::F8 => KeyCode::F(8),
+        TKeyCode::F9 => KeyCode::F(9),
+        TKeyCode::F10 => KeyCode::F(10),
+        TKeyCode::F11 => KeyCode::F(11),
+        TKeyCode::F12 => KeyCode::F(12),
+        TKeyCode::NumPad0 => KeyCode::NumPad(0),
+        TKeyCode::NumPad1 => KeyCode::NumPad(1),
+        TKeyCode::NumPad2 => KeyCode::NumPad(2),
+        TKeyCode::NumPad3 => KeyCode::NumPad(3),
+        TKeyCode::NumPad4 => KeyCode::NumPad(4),
+        TKeyCode::NumPad5 => KeyCode::NumPad(5),
+        TKeyCode::NumPad6 => KeyCode::NumPad(6),
+        TKeyCode::NumPad7 => KeyCode::NumPad(7),
+        TKeyCode::NumPad8 => KeyCode::NumPad(8),
+        TKeyCode::NumPad9 => KeyCode::NumPad(9),
+        TKeyCode::Spacebar => KeyCode::Char(' '),
+        // Digits, letters and the keypad's operators all come with
+        // the character they type
+        _ if keypress.printable != '\0' => KeyCode::Char(keypress.printable),
+        _ => return None,
+    };
+
+    let modifiers = KeyModifiers {
+        shift: keypress.shift,
+        ctrl: keypress.ctrl,
+        alt: keypress.alt,
+    };
+
+    Some(Key::with_modifiers(code, modifiers))
+}
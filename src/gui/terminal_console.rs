@@ -0,0 +1,423 @@
+use gui::{Alignment, Cell, Color, Console, HeadlessConsole, Key, KeyCode, KeyModifiers, MouseButton, MouseEvent};
+use util::units::{Point, Size};
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::str;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long each frame lasts at most, matching the window's 60 frames per second
+const FRAME_TIME_MS: u64 = 1000 / 60;
+
+/// Something read from the terminal
+enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    /// The user pressed Ctrl-C, or the terminal went away
+    Quit,
+}
+
+/// A console drawn in a text terminal with ANSI escape codes.
+///
+/// The terminal is switched into raw mode for as long as the console exists
+/// so that keys are read as soon as they are pressed. Drawing happens in
+/// memory, and each flush only rewrites the cells which have changed since
+/// the last one.
+pub struct TerminalConsole {
+    screen: HeadlessConsole,
+    /// The cells as they currently appear in the terminal
+    drawn: Vec<Option<Cell>>,
+    events: Receiver<Event>,
+    /// Keys and mouse events which have been read but not yet asked for
+    keys: VecDeque<Key>,
+    mouse: VecDeque<MouseEvent>,
+    saved_mode: String,
+    last_flush: Instant,
+    closed: bool,
+}
+
+impl TerminalConsole {
+    /// Takes over the terminal, failing if it is smaller than `size`
+    pub fn new(size: Size) -> io::Result<TerminalConsole> {
+        let (rows, columns) = terminal_size()?;
+        if columns < size.x || rows < size.y {
+            return Err(io::Error::new(io::ErrorKind::Other, format!(
+                "the terminal must be at least {}x{} characters, but is {}x{}",
+                size.x, size.y, columns, rows)));
+        }
+
+        let saved_mode = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+
+        // Use the alternate screen, hide the cursor, make the keypad send
+        // its own codes rather than digits and report every mouse movement
+        print!("\x1b[?1049h\x1b[?25l\x1b=\x1b[?1003h\x1b[?1006h\x1b[0m\x1b[2J");
+        io::stdout().flush()?;
+
+        Ok(TerminalConsole {
+            screen: HeadlessConsole::new(size),
+            drawn: vec![None; (size.x * size.y) as usize],
+            events: read_events(),
+            keys: VecDeque::new(),
+            mouse: VecDeque::new(),
+            saved_mode: saved_mode.trim().to_string(),
+            last_flush: Instant::now(),
+            closed: false,
+        })
+    }
+
+    /// Sorts everything read from the terminal so far into keys and mouse
+    /// events
+    fn poll(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Key(key) => self.keys.push_back(key),
+                Event::Mouse(mouse) => self.mouse.push_back(mouse),
+                Event::Quit => self.closed = true,
+            }
+        }
+    }
+}
+
+impl Drop for TerminalConsole {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?1006l\x1b[?1003l\x1b>\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved_mode]);
+    }
+}
+
+impl Console for TerminalConsole {
+    fn put_plain(&mut self, pos: Point, c: char) {
+        self.screen.put_plain(pos, c);
+    }
+
+    fn put(&mut self, pos: Point, c: char, f_color: Color, b_color: Color) {
+        self.screen.put(pos, c, f_color, b_color);
+    }
+
+    fn print_plain(&mut self, pos: Point, text: &str) {
+        self.screen.print_plain(pos, text);
+    }
+
+    fn print(&mut self, pos: Point, text: &str, f_color: Color, b_color: Color) {
+        self.screen.print(pos, text, f_color, b_color);
+    }
+
+    fn print_align(&mut self, pos: Point, text: &str, alignment: Alignment) {
+        self.screen.print_align(pos, text, alignment);
+    }
+
+    fn clear(&mut self) {
+        self.screen.clear();
+    }
+
+    fn flush(&mut self) {
+        let size = self.screen.size();
+        let mut output = String::new();
+        let mut cursor = None;
+        let mut colors = None;
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let pos = Point::new(x, y);
+                let cell = self.screen.cell(pos).expect("Every point on the screen has a cell");
+                let drawn = &mut self.drawn[(y * size.x + x) as usize];
+
+                if *drawn == Some(cell) {
+                    continue;
+                }
+
+                if cursor != Some(pos) {
+                    let _ = write!(output, "\x1b[{};{}H", y + 1, x + 1);
+                }
+
+                if colors != Some((cell.f_color, cell.b_color)) {
+                    let (f, b) = (cell.f_color, cell.b_color);
+                    let _ = write!(output, "\x1b[38;2;{};{};{};48;2;{};{};{}m", f.r, f.g, f.b, b.r, b.g, b.b);
+                    colors = Some((f, b));
+                }
+
+                output.push(terminal_char(cell.c));
+                cursor = Some(pos.right(1));
+                *drawn = Some(cell);
+            }
+        }
+
+        if !output.is_empty() {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush());
+        }
+
+        let frame_time = Duration::from_millis(FRAME_TIME_MS);
+        let elapsed = self.last_flush.elapsed();
+        if elapsed < frame_time {
+            thread::sleep(frame_time - elapsed);
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn check_for_keypress(&mut self) -> Option<Key> {
+        self.poll();
+        self.keys.pop_front()
+    }
+
+    fn check_for_mouse(&mut self) -> Option<MouseEvent> {
+        self.poll();
+        self.mouse.pop_front()
+    }
+
+    fn size(&self) -> Size {
+        self.screen.size()
+    }
+
+    fn window_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// Runs `stty` on the terminal, returning what it prints
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "stdin is not a terminal"))
+    }
+}
+
+/// Returns the number of rows and columns in the terminal
+fn terminal_size() -> io::Result<(i32, i32)> {
+    let size = stty(&["size"])?;
+    let numbers: Vec<i32> = size.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+
+    match numbers.as_slice() {
+        &[rows, columns] => Ok((rows, columns)),
+        _ => Err(io::Error::new(io::ErrorKind::Other, "could not find the size of the terminal")),
+    }
+}
+
+/// Starts a thread which turns everything typed into events.
+///
+/// Escape sequences arrive from the terminal in a single read, so a lone
+/// escape byte at the end of a read is taken to be the Escape key.
+fn read_events() -> Receiver<Event> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 64];
+
+        loop {
+            let events = match stdin.read(&mut buffer) {
+                Ok(0) | Err(_) => vec![Event::Quit],
+                Ok(n) => parse_input(&buffer[..n]),
+            };
+
+            for event in events {
+                let quit = match event { Event::Quit => true, _ => false };
+
+                if sender.send(event).is_err() || quit {
+                    return;
+                }
+            }
+        }
+    });
+
+    receiver
+}
+
+fn parse_input(bytes: &[u8]) -> Vec<Event> {
+    let mut events = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b if i + 1 < bytes.len() && (bytes[i + 1] == b'[' || bytes[i + 1] == b'O') => {
+                // Control sequences end with a byte from '@' to '~'
+                let start = i + 2;
+                let end = match bytes[start..].iter().position(|&b| b >= 0x40 && b <= 0x7e) {
+                    Some(p) => start + p,
+                    None => break,
+                };
+
+                let params = str::from_utf8(&bytes[start..end]).unwrap_or("");
+
+                if params.starts_with('<') {
+                    if let Some(mouse) = mouse_event(&params[1..], bytes[end]) {
+                        events.push(Event::Mouse(mouse));
+                    }
+                } else if let Some(key) = escape_sequence_key(bytes[i + 1], params, bytes[end]) {
+                    events.push(Event::Key(key));
+                }
+
+                i = end + 1;
+                continue;
+            }
+            // Escape followed by another key is how terminals send Alt
+            0x1b if i + 1 < bytes.len() => {
+                let (key, length) = byte_key(&bytes[i + 1..]);
+
+                if let Some(key) = key {
+                    let modifiers = KeyModifiers { alt: true, ..key.modifiers };
+                    events.push(Event::Key(Key::with_modifiers(key.code, modifiers)));
+                }
+
+                i += 1 + length;
+                continue;
+            }
+            // Ctrl-C and Ctrl-D
+            0x03 | 0x04 => events.push(Event::Quit),
+            _ => {
+                let (key, length) = byte_key(&bytes[i..]);
+
+                if let Some(key) = key {
+                    events.push(Event::Key(key));
+                }
+
+                i += length;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    events
+}
+
+/// Reads the key at the start of some input which is not part of an escape
+/// sequence, returning it and the number of bytes it took up
+fn byte_key(bytes: &[u8]) -> (Option<Key>, usize) {
+    let key = match bytes[0] {
+        0x1b => Key::new(KeyCode::Escape),
+        b'\r' | b'\n' => Key::new(KeyCode::Enter),
+        b'\t' => Key::new(KeyCode::Tab),
+        0x7f | 0x08 => Key::new(KeyCode::Backspace),
+        // The other control characters are Ctrl and a letter
+        b @ 0x01..=0x1a => Key::ctrl(KeyCode::Char((b'a' + b - 1) as char)),
+        _ => {
+            let length = utf8_length(bytes[0]);
+            let end = cmp::min(length, bytes.len());
+
+            let key = str::from_utf8(&bytes[..end]).ok()
+                                                   .and_then(|text| text.chars().next())
+                                                   .filter(|c| !c.is_control())
+                                                   .map(Key::char);
+            return (key, end);
+        }
+    };
+
+    (Some(key), 1)
+}
+
+/// Translates `ESC [ ...` and `ESC O ...` sequences into keys.
+///
+/// Modifiers arrive as the second parameter, as in `ESC [ 1 ; 5 A` for
+/// Ctrl-Up, holding one more than a bit set of Shift (1), Alt (2) and
+/// Ctrl (4).
+fn escape_sequence_key(kind: u8, params: &str, last: u8) -> Option<Key> {
+    let numbers: Vec<u32> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    let number = numbers[0];
+
+    let modifiers = match numbers.get(1) {
+        Some(&m) if m > 1 => KeyModifiers { shift: (m - 1) & 1 != 0, alt: (m - 1) & 2 != 0, ctrl: (m - 1) & 4 != 0 },
+        _ => KeyModifiers::none(),
+    };
+
+    let code = match (kind, last) {
+        (_, b'A') => KeyCode::Up,
+        (_, b'B') => KeyCode::Down,
+        (_, b'C') => KeyCode::Right,
+        (_, b'D') => KeyCode::Left,
+        (_, b'H') => KeyCode::Home,
+        (_, b'F') => KeyCode::End,
+        (b'[', b'Z') => return Some(Key::shift(KeyCode::Tab)),
+        (_, b'P') => KeyCode::F(1),
+        (_, b'Q') => KeyCode::F(2),
+        (_, b'R') => KeyCode::F(3),
+        (_, b'S') => KeyCode::F(4),
+        (b'[', b'~') => match number {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            11..=15 => KeyCode::F((number - 10) as u8),
+            17..=21 => KeyCode::F((number - 11) as u8),
+            23 | 24 => KeyCode::F((number - 12) as u8),
+            _ => return None,
+        },
+        // The keypad in application mode
+        (b'O', b'M') => KeyCode::Enter,
+        (b'O', b'p'..=b'y') => KeyCode::NumPad(last - b'p'),
+        _ => return None,
+    };
+
+    Some(Key::with_modifiers(code, modifiers))
+}
+
+/// Translates a mouse report, `ESC [ < button ; x ; y M`, into a mouse event.
+///
+/// Presses end in `M` and releases in `m`. The button number has 32 added
+/// when the mouse is moving, and 64 added for the scroll wheel.
+fn mouse_event(params: &str, last: u8) -> Option<MouseEvent> {
+    let numbers: Vec<i32> = params.split(';').filter_map(|p| p.parse().ok()).collect();
+
+    let (button, x, y) = match numbers.as_slice() {
+        &[button, x, y] => (button, x, y),
+        _ => return None,
+    };
+
+    // Reports count from one rather than zero
+    let pos = Point::new(x - 1, y - 1);
+
+    if button & 32 != 0 {
+        return Some(MouseEvent::Moved(pos));
+    }
+
+    if last != b'M' || button & 64 != 0 {
+        return None;
+    }
+
+    match button & 3 {
+        0 => Some(MouseEvent::Pressed(MouseButton::Left, pos)),
+        1 => Some(MouseEvent::Pressed(MouseButton::Middle, pos)),
+        2 => Some(MouseEvent::Pressed(MouseButton::Right, pos)),
+        _ => None,
+    }
+}
+
+fn utf8_length(first_byte: u8) -> usize {
+    match first_byte {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    }
+}
+
+/// Replaces the characters which only exist in the game's font with the
+/// closest Unicode equivalents
+fn terminal_char(c: char) -> char {
+    match c {
+        '\u{0}' | '\u{3}' => '│',
+        '\u{1}' | '\u{2}' => '─',
+        '\u{4}' => '┐',
+        '\u{5}' => '└',
+        '\u{6}' => '┌',
+        '\u{7}' => '┘',
+        '\u{8}' => '▐',
+        '\u{9}' => '▌',
+        '\u{80}' | '\u{81}' | '\u{82}' => '█',
+        c if c.is_control() => '?',
+        c => c,
+    }
+}
@@ -1,8 +1,12 @@
-use gui::{Color, Console};
+use engine::log::Span;
+use gui::{Alignment, Color, Console};
 use util::units::{BorderedRectangle, Point, Size};
 
-use tcod;
-
+/// An area of the console inside a border.
+///
+/// Positions are relative to the top left of the area inside the border, and
+/// anything drawn outside that area is clipped so that it cannot spill over
+/// the border into neighbouring widgets.
 pub struct Widget {
     pub rect: BorderedRectangle,
 }
@@ -12,24 +16,77 @@ impl Widget {
         Widget { rect: BorderedRectangle::new(location, size) }
     }
 
+    /// Returns the number of columns and rows which can be drawn to
+    pub fn inner_size(&self) -> Size {
+        self.rect.inner_size() + (1, 1)
+    }
+
+    /// Returns true if a position lies inside the widget's border
+    pub fn contains(&self, pos: Point) -> bool {
+        let size = self.inner_size();
+        pos.x >= 0 && pos.y >= 0 && pos.x < size.x && pos.y < size.y
+    }
+
+    /// Converts a position on the console into one relative to the widget,
+    /// if it lies inside the widget's border
+    pub fn local_position(&self, console_pos: Point) -> Option<Point> {
+        let pos = console_pos - self.rect.inner_location();
+        if self.contains(pos) { Some(pos) } else { None }
+    }
+
     pub fn put_plain(&self, console: &mut Console, pos: Point, c: char) {
-        console.put_plain(self.adjusted_position(pos), c);
+        if self.contains(pos) {
+            console.put_plain(self.adjusted_position(pos), c);
+        }
     }
 
     pub fn put(&self, console: &mut Console, pos: Point, c: char, f_color: Color, b_color: Color) {
-        console.put(self.adjusted_position(pos), c, f_color, b_color);
+        if self.contains(pos) {
+            console.put(self.adjusted_position(pos), c, f_color, b_color);
+        }
     }
 
     pub fn print_plain(&self, console: &mut Console, pos: Point, text: &str) {
-        console.print_plain(self.adjusted_position(pos), text);
+        if let Some(text) = self.clip(pos, text) {
+            console.print_plain(self.adjusted_position(pos), &text);
+        }
     }
 
     pub fn print(&self, console: &mut Console, pos: Point, text: &str, f_color: Color, b_color: Color) {
-        console.print(self.adjusted_position(pos), text, f_color, b_color);
+        if let Some(text) = self.clip(pos, text) {
+            console.print(self.adjusted_position(pos), &text, f_color, b_color);
+        }
     }
 
-    pub fn print_align(&self, console: &mut Console, pos: Point, text: &str, alignment: tcod::TextAlignment) {
-        console.print_align(self.adjusted_position(pos), text, alignment);
+    /// Prints a line of coloured spans, using `color` for any without a
+    /// colour of their own
+    pub fn print_spans(&self, console: &mut Console, pos: Point, spans: &[Span], color: Color, b_color: Color) {
+        let mut pos = pos;
+
+        for span in spans {
+            let f_color = span.color.unwrap_or(color);
+            self.print(console, pos, &span.text, f_color, b_color);
+            pos = pos.right(span.text.chars().count() as i32);
+        }
+    }
+
+    /// Prints aligned text. Only the row is checked against the widget's
+    /// bounds, as the width of aligned text depends on the alignment.
+    pub fn print_align(&self, console: &mut Console, pos: Point, text: &str, alignment: Alignment) {
+        if pos.y >= 0 && pos.y < self.inner_size().y {
+            console.print_align(self.adjusted_position(pos), text, alignment);
+        }
+    }
+
+    /// Cuts a single line of text off at the widget's right edge, returning
+    /// `None` if none of it would be visible
+    fn clip(&self, pos: Point, text: &str) -> Option<String> {
+        if !self.contains(pos) {
+            return None;
+        }
+
+        let width = (self.inner_size().x - pos.x) as usize;
+        Some(text.chars().take(width).collect())
     }
 
     // TODO: How to clear a subset of the console?
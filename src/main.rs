@@ -1,14 +1,140 @@
 extern crate tcod;
 extern crate verbonia;
 
-use verbonia::gui::{Console, GUI};
-use verbonia::engine::Game;
+use verbonia::gui::{Console, GUI, TcodConsole, TerminalConsole};
+use verbonia::gui::keymap;
+use verbonia::gui::screens::{MenuScreen, ReplayScreen};
+use verbonia::engine::{replay, scheduler, tiles, Game, Replay};
 use verbonia::util::units::Size;
 
+use std::env;
+use std::path::Path;
+use std::process;
+
 fn main() {
-    let game = Game::new();
-    let console = Console::new(Size::new(80, 50));
-    let mut gui = GUI::new(game, console);
+    // The built-in tiles stay in place if the file cannot be loaded
+    if let Err(e) = tiles::load_file("assets/tiles.txt") {
+        eprintln!("Could not load tile definitions from assets/tiles.txt, using the built-in ones: {}", e);
+    }
+
+    // Likewise the built-in key bindings
+    if Path::new(keymap::DEFAULT_PATH).exists() {
+        if let Err(e) = keymap::load_file(keymap::DEFAULT_PATH) {
+            eprintln!("Could not load key bindings from {}, using the built-in ones: {}", keymap::DEFAULT_PATH, e);
+        }
+    }
+
+    let mut use_terminal = false;
+    let mut headless = false;
+    let mut seed = None;
+    let mut record_path = Some(replay::DEFAULT_PATH.to_string());
+    let mut replay_path = None;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--terminal" => use_terminal = true,
+            "--headless" => headless = true,
+            "--seed" => {
+                match args.next().and_then(|s| s.parse::<u64>().ok()) {
+                    Some(s) => seed = Some(s),
+                    None => usage("--seed needs a number"),
+                }
+            }
+            "--record" => {
+                match args.next() {
+                    Some(path) => record_path = Some(path),
+                    None => usage("--record needs a path"),
+                }
+            }
+            "--no-record" => record_path = None,
+            "--replay" => {
+                match args.next() {
+                    Some(path) => replay_path = Some(path),
+                    None => usage("--replay needs a path"),
+                }
+            }
+            _ => usage(&format!("Unknown argument `{}`", arg)),
+        }
+    }
+
+    if headless && replay_path.is_none() {
+        usage("--headless can only be used with --replay");
+    }
+
+    let replay = replay_path.map(|path| {
+        match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Could not load the replay from {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+    });
+
+    if headless {
+        run_headless(replay.expect("--headless requires a replay"));
+        return;
+    }
+
+    let size = Size::new(80, 50);
+    let console: Box<Console> = if use_terminal {
+        match TerminalConsole::new(size) {
+            Ok(console) => Box::new(console),
+            Err(e) => {
+                eprintln!("Could not use the terminal: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        Box::new(TcodConsole::new(size))
+    };
+
+    let mut gui = match replay {
+        Some(replay) => {
+            let game = replay.new_game();
+            GUI::with_screen(game, console, ReplayScreen::new(replay))
+        }
+        None => {
+            let game = match seed {
+                Some(seed) => Game::with_seed(seed),
+                None => Game::new(),
+            };
+
+            // Recording starts once the player picks Start Game, so that
+            // continuing a saved game leaves the last recording alone
+            GUI::with_screen(game, console, MenuScreen::recording_to(record_path))
+        }
+    };
 
     gui.run();
 }
+
+/// Plays a replay to the end without displaying it, then describes how the
+/// game ended
+fn run_headless(mut replay: Replay) {
+    let mut game = replay.new_game();
+    let result = replay.run(&mut game);
+
+    let entities = &game.world.entities;
+    let player = game.world.player();
+    println!("Seed:     {}", replay.seed());
+    println!("Commands: {}/{}", replay.position(), replay.len());
+    println!("Turn:     {}", game.turn);
+    println!("Time:     {}", scheduler::format_time(game.time));
+    println!("Depth:    {}", game.world.depth);
+    println!("Health:   {}/{}{}", entities.health(player), entities.max_health(player),
+             if entities.is_dead(player) { " (dead)" } else { "" });
+
+    if let Err(e) = result {
+        eprintln!("The replay stopped early: {}", e);
+        process::exit(1);
+    }
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("Usage: verbonia [--terminal] [--seed <number>] [--record <path> | --no-record]");
+    eprintln!("       verbonia --replay <path> [--terminal | --headless]");
+    process::exit(1);
+}
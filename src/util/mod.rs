@@ -2,8 +2,9 @@
 
 pub mod units;
 
-pub trait FromChar {
-    type Ret;
+/// Conversion from a single character, which may fail
+pub trait FromChar: Sized {
+    type Err;
 
-    fn from_char(c: char) -> Self::Ret;
+    fn from_char(c: char) -> Result<Self, Self::Err>;
 }
@@ -0,0 +1,19 @@
+/// A colour, independent of how it is eventually drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Color {
+        Color { r: r, g: g, b: b }
+    }
+
+    /// Blends towards another colour, from this one at 0 to the other at 1
+    pub fn lerp(&self, to: Color, amount: f32) -> Color {
+        let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount) as u8;
+        Color::new(channel(self.r, to.r), channel(self.g, to.g), channel(self.b, to.b))
+    }
+}
@@ -1,4 +1,4 @@
-use util::units::AsTuple;
+use util::units::{AsTuple, Offset};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Direction {
@@ -6,16 +6,90 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    /// Staying in place
+    Wait,
+}
+
+/// Every direction which involves moving, clockwise from `Up`
+static CLOCKWISE: [Direction; 8] = [
+    Direction::Up,
+    Direction::UpRight,
+    Direction::Right,
+    Direction::DownRight,
+    Direction::Down,
+    Direction::DownLeft,
+    Direction::Left,
+    Direction::UpLeft,
+];
+
+impl Direction {
+    /// Returns every direction which involves moving, clockwise from `Up`
+    pub fn all() -> &'static [Direction; 8] {
+        &CLOCKWISE
+    }
+
+    /// Returns the direction pointing the opposite way
+    pub fn opposite(&self) -> Direction {
+        self.rotate(4)
+    }
+
+    /// Returns the direction 45 degrees clockwise from this one
+    pub fn rotate_cw(&self) -> Direction {
+        self.rotate(1)
+    }
+
+    /// Returns the direction 45 degrees anticlockwise from this one
+    pub fn rotate_ccw(&self) -> Direction {
+        self.rotate(7)
+    }
+
+    /// Returns the direction which best matches an offset, looking only at
+    /// the sign of each component
+    pub fn from_offset(offset: Offset) -> Direction {
+        match (offset.x.signum(), offset.y.signum()) {
+            ( 0, -1) => Direction::Up,
+            ( 0,  1) => Direction::Down,
+            (-1,  0) => Direction::Left,
+            ( 1,  0) => Direction::Right,
+            (-1, -1) => Direction::UpLeft,
+            ( 1, -1) => Direction::UpRight,
+            (-1,  1) => Direction::DownLeft,
+            ( 1,  1) => Direction::DownRight,
+            _        => Direction::Wait,
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let (x, y) = self.as_tuple();
+        x != 0 && y != 0
+    }
+
+    /// Rotates clockwise in steps of 45 degrees. `Wait` has no direction,
+    /// so it stays the same.
+    fn rotate(&self, steps: usize) -> Direction {
+        match CLOCKWISE.iter().position(|d| d == self) {
+            Some(index) => CLOCKWISE[(index + steps) % CLOCKWISE.len()],
+            None => Direction::Wait,
+        }
+    }
 }
 
 impl AsTuple<i32> for Direction {
     fn as_tuple(&self) -> (i32, i32) {
         match *self {
-            Direction::Up      => (  0, -1),
-            Direction::Down    => (  0,  1),
-            Direction::Left    => ( -1,  0),
-            Direction::Right   => (  1,  0),
+            Direction::Up        => (  0, -1),
+            Direction::Down      => (  0,  1),
+            Direction::Left      => ( -1,  0),
+            Direction::Right     => (  1,  0),
+            Direction::UpLeft    => ( -1, -1),
+            Direction::UpRight   => (  1, -1),
+            Direction::DownLeft  => ( -1,  1),
+            Direction::DownRight => (  1,  1),
+            Direction::Wait      => (  0,  0),
         }
     }
 }
-
@@ -1,9 +1,11 @@
+mod color;
 mod direction;
 mod offset;
 mod point;
 mod rectangle;
 mod size;
 
+pub use self::color::Color;
 pub use self::direction::Direction;
 pub use self::offset::Offset;
 pub use self::point::Point;
@@ -34,6 +34,24 @@ impl Rectangle {
     pub fn height(&self) -> i32 {
         return self.size.y;
     }
+
+    pub fn center(&self) -> Point {
+        return self.location + (self.size.x / 2, self.size.y / 2);
+    }
+
+    /// Returns true if the point lies inside the rectangle
+    pub fn contains(&self, point: Point) -> bool {
+        return point.x >= self.location.x && point.x < self.location.x + self.size.x
+            && point.y >= self.location.y && point.y < self.location.y + self.size.y;
+    }
+
+    /// Returns true if the two rectangles overlap
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        return self.location.x < other.location.x + other.size.x
+            && other.location.x < self.location.x + self.size.x
+            && self.location.y < other.location.y + other.size.y
+            && other.location.y < self.location.y + self.size.y;
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]